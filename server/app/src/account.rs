@@ -0,0 +1,202 @@
+use crate::data_store::DataStore;
+use crate::error::Result;
+use crate::file_data_store::FileDataStore;
+use crate::history::{self, History};
+use crate::json_file;
+use crate::reddit_client::{AuthRedditClient, RateBudget};
+use crate::schedule::{self, Schedule};
+use crate::user;
+use log::{error, info};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+const ACCOUNTS_DIR: &str = "accounts";
+const TICK_SECONDS: u64 = 30;
+
+/// A reddit account with its own history, schedule and rate limit budget, kept in
+/// `DATA_DIR/accounts/{name}`.
+pub struct Account {
+    pub name: String,
+    pub history: History,
+    pub schedule: Schedule,
+    rate_budget: Arc<RateBudget>,
+}
+
+fn account_dir(data_dir: &str, name: &str) -> PathBuf {
+    PathBuf::from(data_dir)
+        .join(ACCOUNTS_DIR)
+        .join(name.to_lowercase())
+}
+
+impl Account {
+    pub fn load(data_dir: &str, name: &str) -> Result<Account> {
+        let dir = account_dir(data_dir, name);
+        let dir = dir.to_string_lossy();
+        Ok(Account {
+            name: name.to_string(),
+            history: History::load(&dir)?,
+            schedule: Schedule::load(&dir)?,
+            rate_budget: Arc::new(RateBudget::new()),
+        })
+    }
+
+    /// Clients of the same account share its rate limit budget.
+    pub fn client(&self, auth_token: String) -> AuthRedditClient {
+        AuthRedditClient::with_budget(auth_token, self.rate_budget.clone())
+    }
+}
+
+/// Accounts loaded on first use.
+pub struct Accounts {
+    data_dir: String,
+    loaded: RwLock<HashMap<String, Arc<Account>>>,
+}
+
+impl Accounts {
+    pub fn new(data_dir: &str) -> Accounts {
+        Accounts {
+            data_dir: data_dir.to_string(),
+            loaded: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<Arc<Account>> {
+        let key = name.to_lowercase();
+        if let Some(account) = self
+            .loaded
+            .read()
+            .expect("can't obtain the lock to read accounts")
+            .get(&key)
+        {
+            return Ok(account.clone());
+        }
+        let mut loaded = self
+            .loaded
+            .write()
+            .expect("can't obtain the lock to load account");
+        if let Some(account) = loaded.get(&key) {
+            return Ok(account.clone());
+        }
+        let account = Arc::new(Account::load(&self.data_dir, name)?);
+        loaded.insert(key, account.clone());
+        Ok(account)
+    }
+
+    /// Submits due scheduled posts and takes performance snapshots of every logged in account,
+    /// forever.
+    pub async fn run_periodically(&self, data_store: &impl DataStore) {
+        loop {
+            for name in data_store.accounts() {
+                let (account, auth_token) =
+                    match (self.get(&name), data_store.find_auth_token(&name)) {
+                        (Ok(account), Some(auth_token)) => (account, auth_token),
+                        (Err(error), _) => {
+                            error!("Can't load account {}: {}", name, error);
+                            continue;
+                        }
+                        (_, None) => continue,
+                    };
+                let client = account.client(auth_token);
+                account
+                    .schedule
+                    .publish_due(&client, &account.history)
+                    .await;
+                account.history.snapshot_due(&client).await;
+            }
+            actix_web::rt::time::sleep(std::time::Duration::from_secs(TICK_SECONDS)).await;
+        }
+    }
+}
+
+/// Moves what the versions which knew a single account stored, the token in `session.json` and
+/// `DATA_DIR/schedule.json` and `history.json`, to the account of the token. The files go to the
+/// default account when the token is gone or expired, they stay where they are without one.
+pub async fn migrate_legacy(data_dir: &str, data_store: &FileDataStore) {
+    if let Some(auth_token) = data_store.legacy_auth_token() {
+        match user::me(&AuthRedditClient::new(auth_token)).await {
+            Ok(user) => data_store.adopt_legacy_auth_token(&user.name),
+            Err(error) => error!("Can't find the account of the stored login: {}", error),
+        }
+    }
+    if let Some(account) = data_store.default_account() {
+        if let Err(error) = migrate_legacy_files(data_dir, &account) {
+            error!(
+                "Can't move the schedule and history to {}: {}",
+                account, error
+            );
+        }
+    }
+}
+
+/// Moves `DATA_DIR/schedule.json` and `history.json` into the directory of `account`, entries
+/// it has already are kept.
+pub fn migrate_legacy_files(data_dir: &str, account: &str) -> Result<()> {
+    for file in [schedule::SCHEDULE_FILE, history::HISTORY_FILE] {
+        let legacy = PathBuf::from(data_dir).join(file);
+        if !legacy.exists() {
+            continue;
+        }
+        let path = account_dir(data_dir, account).join(file);
+        let mut entries: Vec<Value> = json_file::read(&path)?;
+        let legacy_entries: Vec<Value> = json_file::read(&legacy)?;
+        for entry in legacy_entries {
+            if !entries.iter().any(|known| known["id"] == entry["id"]) {
+                entries.push(entry);
+            }
+        }
+        json_file::write(&path, &entries)?;
+        fs::remove_file(&legacy)?;
+        info!("Moved {} to account {}", legacy.display(), account);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{final_post, submission};
+    use chrono::Utc;
+
+    #[test]
+    fn single_account_data_moves_to_the_account_of_its_token() {
+        let dir = std::env::temp_dir().join(format!("account-test-{}", uuid::Uuid::new_v4()));
+        let data_dir = dir.to_str().unwrap();
+        let now = Utc::now();
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("session.json"), r#"{"auth_token": "legacy"}"#).unwrap();
+        let scheduled = Schedule::load(data_dir)
+            .unwrap()
+            .add(
+                final_post("rust", "https://example.com"),
+                now,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        History::load(data_dir)
+            .unwrap()
+            .record(submission("abc", "rust", "https://example.com", now))
+            .unwrap();
+        let store = FileDataStore::new(data_dir);
+
+        store.adopt_legacy_auth_token("Me");
+        migrate_legacy_files(data_dir, "Me").unwrap();
+
+        assert_eq!(store.legacy_auth_token(), None);
+        assert_eq!(store.find_auth_token("me"), Some(String::from("legacy")));
+        assert_eq!(store.default_account(), Some(String::from("Me")));
+        let account = Account::load(data_dir, "Me").unwrap();
+        let scheduled_ids: Vec<String> =
+            account.schedule.list().into_iter().map(|s| s.id).collect();
+        assert_eq!(scheduled_ids, vec![scheduled.id]);
+        let history_ids: Vec<String> = account.history.list().into_iter().map(|s| s.id).collect();
+        assert_eq!(history_ids, vec!["abc"]);
+        assert!(!dir.join(schedule::SCHEDULE_FILE).exists());
+        assert!(!dir.join(history::HISTORY_FILE).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
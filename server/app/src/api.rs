@@ -0,0 +1,986 @@
+use actix_web::dev::Payload;
+use actix_web::http::header;
+use actix_web::web::{self, Bytes, Data, Json, Path, Query};
+use actix_web::{delete, get, patch, post, FromRequest, HttpRequest, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use futures::stream;
+use log::{error, info};
+use serde_derive::{Deserialize, Serialize};
+use std::future::{ready, Ready};
+use std::sync::Arc;
+use std::{fs, io};
+use subreddit_posts_logic::account::{Account, Accounts};
+use subreddit_posts_logic::api_error::{ApiError, ErrorEnvelope};
+use subreddit_posts_logic::api_key::API_KEY_HEADER;
+use subreddit_posts_logic::best_time::{
+    best_time_for, parse_timezone, BestTimeReport, SamplingSettings,
+};
+use subreddit_posts_logic::comment::delete_all_comments;
+use subreddit_posts_logic::data_store::DataStore;
+use subreddit_posts_logic::environment::{AuthFlow, Environment};
+use subreddit_posts_logic::export::{export_history, ExportSummary};
+use subreddit_posts_logic::file_data_store::FileDataStore;
+use subreddit_posts_logic::flairs::{retrieve_flairs_for, FlairInfo};
+use subreddit_posts_logic::history::{Chart, Submission};
+use subreddit_posts_logic::job::{ItemStatus, Job, Jobs, Progress};
+use subreddit_posts_logic::login::{login_url, password_token, request_login, store_login};
+use subreddit_posts_logic::performance::{performance_for, PerformanceReport};
+use subreddit_posts_logic::post::{
+    delete_with_upvotes_lt, plan, post, read_posts_files, Plan, Posts, SubmissionResult,
+};
+use subreddit_posts_logic::reddit_client::AuthRedditClient;
+use subreddit_posts_logic::removal::{removal_report, SubredditRemovals};
+use subreddit_posts_logic::retention::{RetentionRun, RetentionRunner};
+use subreddit_posts_logic::schedule::ScheduledPost;
+use subreddit_posts_logic::subreddit::{MatchMode, PageRequest, SubredditMatcher, SubredditReport};
+use subreddit_posts_logic::user::{self, User};
+use subreddit_posts_logic::{cooldown, subreddit};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{IntoParams, Modify, OpenApi, ToSchema};
+
+const ACCOUNT_HEADER: &str = "X-Reddit-Account";
+
+type ApiResult<T> = std::result::Result<T, ApiError>;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Subreddit posts API",
+        description = "Requests act for the account named by the `X-Reddit-Account` header or \
+                       the `account` query parameter, otherwise for the default account. Long \
+                       operations answer 202 with a job id to follow under `/api/v1/jobs`."
+    ),
+    paths(
+        login,
+        list_accounts,
+        me,
+        submit_posts,
+        plan_posts,
+        submit_posts_files,
+        plan_posts_files,
+        delete_posts,
+        delete_comments,
+        subreddit_activity,
+        delete_subreddit_activity,
+        delete_subreddit_comments,
+        best_time,
+        list_subreddits,
+        flairs,
+        export,
+        performance,
+        removals,
+        retention,
+        list_schedule,
+        reschedule,
+        cancel_scheduled,
+        list_history,
+        history_chart,
+        show_job,
+        cancel_job,
+        job_events,
+    ),
+    components(schemas(ErrorEnvelope, FlairInfo, SubmissionResult)),
+    modifiers(&SecuritySchemes),
+    security(("bearer" = []), ("api_key" = []))
+)]
+pub struct ApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(API_KEY_HEADER))),
+        );
+    }
+}
+
+pub fn configure(config: &mut web::ServiceConfig) {
+    config
+        .service(openapi_json)
+        .service(login)
+        .service(list_accounts)
+        .service(me)
+        .service(submit_posts)
+        .service(plan_posts)
+        .service(submit_posts_files)
+        .service(plan_posts_files)
+        .service(delete_posts)
+        .service(delete_comments)
+        .service(subreddit_activity)
+        .service(delete_subreddit_activity)
+        .service(delete_subreddit_comments)
+        .service(best_time)
+        .service(list_subreddits)
+        .service(flairs)
+        .service(export)
+        .service(performance)
+        .service(removals)
+        .service(retention)
+        .service(list_schedule)
+        .service(reschedule)
+        .service(cancel_scheduled)
+        .service(list_history)
+        .service(history_chart)
+        .service(show_job)
+        .service(cancel_job)
+        .service(job_events);
+}
+
+#[get("/api/v1/openapi.json")]
+async fn openapi_json() -> impl Responder {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+/// Logs in with the password flow right away, otherwise starts an OAuth login which finishes
+/// once reddit redirected the browser to `/reddit/login-callback`.
+#[utoipa::path(
+    tag = "accounts",
+    responses(
+        (status = 200, body = LoginResponse),
+        (status = 502, body = ErrorEnvelope, description = "Reddit refused the password login"),
+    )
+)]
+#[post("/api/v1/login")]
+async fn login(data: Data<FileDataStore>) -> ApiResult<Json<LoginResponse>> {
+    let settings = Environment::read_env();
+    info!("Calling login");
+    if settings.auth_flow == AuthFlow::Password {
+        let token = password_token(&settings).await?;
+        let account = store_login(data.as_ref(), token).await?;
+        return Ok(Json(LoginResponse::LoggedIn { account }));
+    }
+    let login_request = if settings.headless {
+        login_url(settings)
+    } else {
+        request_login(settings).await
+    };
+    data.store_login_request_id(login_request.id.clone());
+    Ok(Json(LoginResponse::Pending {
+        id: login_request.id,
+        url: login_request.url,
+    }))
+}
+
+#[utoipa::path(tag = "accounts", responses((status = 200, body = AccountList)))]
+#[get("/api/v1/accounts")]
+async fn list_accounts(data: Data<FileDataStore>) -> Json<AccountList> {
+    Json(AccountList {
+        default_account: data.default_account(),
+        accounts: data.accounts(),
+    })
+}
+
+#[utoipa::path(
+    tag = "accounts",
+    params(AccountParams),
+    responses(
+        (status = 200, body = User),
+        (status = 401, body = ErrorEnvelope),
+        (status = 502, body = ErrorEnvelope),
+    )
+)]
+#[get("/api/v1/me")]
+async fn me(Selected { client, .. }: Selected) -> ApiResult<Json<User>> {
+    Ok(Json(user::me(&client).await?))
+}
+
+/// Validates the batch and starts a job submitting its posts one by one.
+#[utoipa::path(
+    tag = "posts",
+    params(AccountParams),
+    request_body = Posts,
+    responses(
+        (status = 202, body = JobStarted),
+        (status = 400, body = ErrorEnvelope, description = "The batch is invalid"),
+        (status = 401, body = ErrorEnvelope),
+    )
+)]
+#[post("/api/v1/posts")]
+async fn submit_posts(
+    (posts, selected, jobs): (Json<Posts>, Selected, Data<Jobs>),
+) -> ApiResult<HttpResponse> {
+    submit(vec![posts.into_inner()], selected, jobs)
+}
+
+/// What submitting the batch would do, without submitting anything.
+#[utoipa::path(
+    tag = "posts",
+    params(AccountParams),
+    request_body = Posts,
+    responses(
+        (status = 200, body = Plan),
+        (status = 400, body = ErrorEnvelope, description = "The batch is invalid"),
+        (status = 401, body = ErrorEnvelope),
+    )
+)]
+#[post("/api/v1/posts/plan")]
+async fn plan_posts((posts, selected): (Json<Posts>, Selected)) -> ApiResult<Json<Plan>> {
+    preview(vec![posts.into_inner()], selected).await
+}
+
+/// Submits the posts files of `POSTS_FILES`.
+#[utoipa::path(
+    tag = "posts",
+    params(AccountParams),
+    responses(
+        (status = 202, body = JobStarted),
+        (status = 400, body = ErrorEnvelope, description = "A file is missing or invalid"),
+        (status = 401, body = ErrorEnvelope),
+    )
+)]
+#[post("/api/v1/posts/files")]
+async fn submit_posts_files((selected, jobs): (Selected, Data<Jobs>)) -> ApiResult<HttpResponse> {
+    submit(
+        read_posts_files(&Environment::read_env().posts_files)?,
+        selected,
+        jobs,
+    )
+}
+
+#[utoipa::path(
+    tag = "posts",
+    params(AccountParams),
+    responses(
+        (status = 200, body = Plan),
+        (status = 400, body = ErrorEnvelope, description = "A file is missing or invalid"),
+        (status = 401, body = ErrorEnvelope),
+    )
+)]
+#[post("/api/v1/posts/files/plan")]
+async fn plan_posts_files(selected: Selected) -> ApiResult<Json<Plan>> {
+    preview(
+        read_posts_files(&Environment::read_env().posts_files)?,
+        selected,
+    )
+    .await
+}
+
+fn validate(batches: &[Posts]) -> ApiResult<()> {
+    let problems: Vec<String> = batches.iter().flat_map(Posts::validate).collect();
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::invalid("the posts are invalid", problems))
+    }
+}
+
+/// The plans of all batches merged into one.
+async fn preview(
+    batches: Vec<Posts>,
+    Selected { account, client }: Selected,
+) -> ApiResult<Json<Plan>> {
+    validate(&batches)?;
+    let cooldowns = cooldown::read_policy();
+    let mut merged = Plan::default();
+    for posts in batches {
+        let schedule = &account.schedule;
+        let history = &account.history;
+        merged.extend(plan(posts, &client, schedule, history, &cooldowns).await?);
+    }
+    Ok(Json(merged))
+}
+
+fn submit(
+    batches: Vec<Posts>,
+    Selected { account, client }: Selected,
+    jobs: Data<Jobs>,
+) -> ApiResult<HttpResponse> {
+    validate(&batches)?;
+    let cooldowns = cooldown::read_policy();
+    let name = account.name.clone();
+    let job_id = jobs.into_inner().spawn("post", &name, |job| async move {
+        let mut report = Vec::new();
+        for posts in batches {
+            let schedule = &account.schedule;
+            let history = &account.history;
+            // a batch which can't be planned fails on its own, the others are still reported
+            let mut unsubmitted = posts.failed();
+            match post(posts, &client, schedule, history, &cooldowns, &job).await {
+                Ok(results) => report.extend(results),
+                Err(error) => {
+                    error!("Can't submit a batch of posts: {}", error);
+                    for result in &mut unsubmitted {
+                        result.warnings.push(error.to_string());
+                    }
+                    report.extend(unsubmitted);
+                }
+            }
+        }
+        Ok(report)
+    });
+    Ok(job_started(job_id))
+}
+
+/// Exports everything, then deletes the own posts with fewer upvotes than `upvotes_below`.
+#[utoipa::path(
+    tag = "posts",
+    params(DeletePostsParams, AccountParams),
+    responses((status = 202, body = JobStarted), (status = 401, body = ErrorEnvelope))
+)]
+#[delete("/api/v1/posts")]
+async fn delete_posts(
+    (params, selected, jobs): (Query<DeletePostsParams>, Selected, Data<Jobs>),
+) -> impl Responder {
+    let Selected { account, client } = selected;
+    let upvotes_below = params.upvotes_below;
+    info!("Deleting post with ups < {}", upvotes_below);
+    let job_id = jobs
+        .into_inner()
+        .spawn("delete-posts", &account.name, |job| async move {
+            let user = user::me(&client).await?;
+            info!("user {:?}", user);
+            let summary =
+                export_history(&client, &user, &Environment::read_env().export_dir).await?;
+            delete_with_upvotes_lt(&client, &user, upvotes_below, &job).await?;
+            info!("Deleted post with ups < {}", upvotes_below);
+            Ok(summary)
+        });
+    job_started(job_id)
+}
+
+/// Exports everything, then deletes all own comments.
+#[utoipa::path(
+    tag = "comments",
+    params(AccountParams),
+    responses((status = 202, body = JobStarted), (status = 401, body = ErrorEnvelope))
+)]
+#[delete("/api/v1/comments")]
+async fn delete_comments((selected, jobs): (Selected, Data<Jobs>)) -> impl Responder {
+    let Selected { account, client } = selected;
+    info!("Deleting all comments");
+    let job_id = jobs
+        .into_inner()
+        .spawn("delete-comments", &account.name, |job| async move {
+            let user = user::me(&client).await?;
+            info!("user {:?}", user);
+            let summary =
+                export_history(&client, &user, &Environment::read_env().export_dir).await?;
+            delete_all_comments(&client, &user, &job).await?;
+            info!("Deleted all comments");
+            Ok(summary)
+        });
+    job_started(job_id)
+}
+
+/// Own posts and comments in the subreddits, `names` is a comma separated list.
+#[utoipa::path(
+    tag = "subreddits",
+    params(
+        ("names" = String, Path, description = "Comma separated subreddit names or patterns"),
+        MatchParams,
+        PageRequest,
+        AccountParams,
+    ),
+    responses(
+        (status = 200, body = SubredditReport),
+        (status = 400, body = ErrorEnvelope, description = "A pattern is invalid"),
+        (status = 401, body = ErrorEnvelope),
+        (status = 502, body = ErrorEnvelope),
+    )
+)]
+#[get("/api/v1/subreddits/{names}/activity")]
+async fn subreddit_activity(
+    (path, params, page, selected): (
+        Path<String>,
+        Query<MatchParams>,
+        Query<PageRequest>,
+        Selected,
+    ),
+) -> ApiResult<Json<SubredditReport>> {
+    let Selected { client, .. } = selected;
+    let names = path.into_inner();
+    info!("Getting info from {}", names);
+    let matcher = subreddit_matcher(&names, &params)?;
+
+    let user = user::me(&client).await?;
+    info!("user {:?}", user);
+
+    let activity = subreddit::get_all_from(&client, &user, &matcher).await?;
+    info!("Info received");
+    Ok(Json(SubredditReport::new(activity, &page)))
+}
+
+/// Exports everything, then deletes the own posts and comments in the subreddits.
+#[utoipa::path(
+    tag = "subreddits",
+    params(
+        ("names" = String, Path, description = "Comma separated subreddit names or patterns"),
+        MatchParams,
+        AccountParams,
+    ),
+    responses(
+        (status = 202, body = JobStarted),
+        (status = 400, body = ErrorEnvelope, description = "A pattern is invalid"),
+        (status = 401, body = ErrorEnvelope),
+    )
+)]
+#[delete("/api/v1/subreddits/{names}/activity")]
+async fn delete_subreddit_activity(
+    (path, params, selected, jobs): (Path<String>, Query<MatchParams>, Selected, Data<Jobs>),
+) -> ApiResult<HttpResponse> {
+    let Selected { account, client } = selected;
+    let names = path.into_inner();
+    info!("Deleting everything from {}", names);
+    let matcher = subreddit_matcher(&names, &params)?;
+    let job_id =
+        jobs.into_inner()
+            .spawn("delete-from-subreddit", &account.name, |job| async move {
+                let user = user::me(&client).await?;
+                info!("user {:?}", user);
+                let summary =
+                    export_history(&client, &user, &Environment::read_env().export_dir).await?;
+                subreddit::delete_all_from(&client, &user, &matcher, &job).await?;
+                Ok(summary)
+            });
+    Ok(job_started(job_id))
+}
+
+/// Exports everything, then deletes the own comments in the subreddits.
+#[utoipa::path(
+    tag = "subreddits",
+    params(
+        ("names" = String, Path, description = "Comma separated subreddit names or patterns"),
+        MatchParams,
+        AccountParams,
+    ),
+    responses(
+        (status = 202, body = JobStarted),
+        (status = 400, body = ErrorEnvelope, description = "A pattern is invalid"),
+        (status = 401, body = ErrorEnvelope),
+    )
+)]
+#[delete("/api/v1/subreddits/{names}/comments")]
+async fn delete_subreddit_comments(
+    (path, params, selected, jobs): (Path<String>, Query<MatchParams>, Selected, Data<Jobs>),
+) -> ApiResult<HttpResponse> {
+    let Selected { account, client } = selected;
+    let names = path.into_inner();
+    info!("Deleting comments from {}", names);
+    let matcher = subreddit_matcher(&names, &params)?;
+    let job_id = jobs.into_inner().spawn(
+        "delete-comments-from-subreddit",
+        &account.name,
+        |job| async move {
+            let user = user::me(&client).await?;
+            info!("user {:?}", user);
+            let summary =
+                export_history(&client, &user, &Environment::read_env().export_dir).await?;
+            subreddit::delete_comments_from(&client, &user, &matcher, &job).await?;
+            Ok(summary)
+        },
+    );
+    Ok(job_started(job_id))
+}
+
+fn subreddit_matcher(names: &str, params: &MatchParams) -> ApiResult<SubredditMatcher> {
+    let names: Vec<String> = names.split(',').map(String::from).collect();
+    Ok(SubredditMatcher::new(
+        &names,
+        params.mode.unwrap_or_default(),
+    )?)
+}
+
+#[utoipa::path(
+    tag = "subreddits",
+    params(("name" = String, Path), SamplingSettings, AccountParams),
+    responses(
+        (status = 200, body = BestTimeReport),
+        (status = 400, body = ErrorEnvelope, description = "The timezone is unknown"),
+        (status = 401, body = ErrorEnvelope),
+        (status = 502, body = ErrorEnvelope),
+    )
+)]
+#[get("/api/v1/subreddits/{name}/best-time")]
+async fn best_time(
+    (path, settings, selected): (Path<String>, Query<SamplingSettings>, Selected),
+) -> ApiResult<Json<BestTimeReport>> {
+    let Selected { client, .. } = selected;
+    let name = path.into_inner();
+    info!("Getting best time to post for {}", name);
+    let timezone = Environment::read_env().timezone;
+    Ok(Json(
+        best_time_for(&client, &name, &settings, &timezone).await?,
+    ))
+}
+
+/// Subreddits listed in `.subreddits`, empty when there is no such file.
+#[utoipa::path(tag = "subreddits", responses((status = 200, body = Vec<String>)))]
+#[get("/api/v1/subreddits")]
+async fn list_subreddits() -> Json<Vec<String>> {
+    Json(read_subreddits_file().unwrap_or_default())
+}
+
+fn read_subreddits_file() -> io::Result<Vec<String>> {
+    let content =
+        fs::read_to_string("server/.subreddits").or_else(|_| fs::read_to_string(".subreddits"))?;
+    Ok(content
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Retrieves the flairs of the subreddits, of the ones in `.subreddits` when none are given.
+/// The job result is a list of `FlairInfo`.
+#[utoipa::path(
+    tag = "subreddits",
+    params(AccountParams),
+    request_body = FlairsRequest,
+    responses(
+        (status = 202, body = JobStarted),
+        (status = 400, body = ErrorEnvelope, description = "There is no `.subreddits` file"),
+        (status = 401, body = ErrorEnvelope),
+    )
+)]
+#[post("/api/v1/flairs")]
+async fn flairs(
+    (request, selected, jobs): (Json<FlairsRequest>, Selected, Data<Jobs>),
+) -> ApiResult<HttpResponse> {
+    let Selected { account, client } = selected;
+    let subreddits = if request.subreddits.is_empty() {
+        read_subreddits_file()
+            .map_err(|error| ApiError::bad_request(format!("no .subreddits file: {}", error)))?
+    } else {
+        request.into_inner().subreddits
+    };
+
+    let job_id = jobs
+        .into_inner()
+        .spawn("flairs", &account.name, |job| async move {
+            let subreddits = subreddits.iter().map(String::as_str).collect();
+            let flair_info = retrieve_flairs_for(subreddits, &client, &job).await;
+            info!("Retrieved flairs: {:?}", flair_info);
+            Ok(flair_info)
+        });
+    Ok(job_started(job_id))
+}
+
+/// Exports the own posts and comments, the job result is an `ExportSummary`.
+#[utoipa::path(
+    tag = "reports",
+    params(AccountParams),
+    responses((status = 202, body = JobStarted), (status = 401, body = ErrorEnvelope))
+)]
+#[post("/api/v1/exports")]
+async fn export((selected, jobs): (Selected, Data<Jobs>)) -> impl Responder {
+    let Selected { account, client } = selected;
+    info!("Exporting posts and comments");
+    let job_id = jobs
+        .into_inner()
+        .spawn("export", &account.name, |job| async move {
+            let item = job.add_items(vec![String::from("posts and comments")]);
+            let user = user::me(&client).await?;
+            info!("user {:?}", user);
+            let summary: ExportSummary =
+                export_history(&client, &user, &Environment::read_env().export_dir).await?;
+            job.finish_item(item, ItemStatus::Done, None);
+            Ok(summary)
+        });
+    job_started(job_id)
+}
+
+#[utoipa::path(
+    tag = "reports",
+    params(PerformanceParams, AccountParams),
+    responses(
+        (status = 200, body = PerformanceReport, content_type = "application/json"),
+        (status = 200, body = String, content_type = "text/csv"),
+        (status = 400, body = ErrorEnvelope, description = "The timezone is unknown"),
+        (status = 401, body = ErrorEnvelope),
+        (status = 502, body = ErrorEnvelope),
+    )
+)]
+#[get("/api/v1/performance")]
+async fn performance(
+    (params, selected): (Query<PerformanceParams>, Selected),
+) -> ApiResult<HttpResponse> {
+    let Selected { client, .. } = selected;
+    info!("Computing performance of own posts");
+    let timezone = params
+        .timezone
+        .clone()
+        .unwrap_or(Environment::read_env().timezone);
+    let tz = parse_timezone(&timezone)?;
+
+    let user = user::me(&client).await?;
+    info!("user {:?}", user);
+
+    let report = performance_for(&client, &user, tz).await?;
+    Ok(match params.format {
+        Some(ReportFormat::Csv) => HttpResponse::Ok()
+            .content_type("text/csv")
+            .body(report.to_csv()?),
+        _ => HttpResponse::Ok().json(report),
+    })
+}
+
+#[utoipa::path(
+    tag = "reports",
+    params(AccountParams),
+    responses(
+        (status = 200, body = Vec<SubredditRemovals>),
+        (status = 401, body = ErrorEnvelope),
+        (status = 502, body = ErrorEnvelope),
+    )
+)]
+#[get("/api/v1/removals")]
+async fn removals(Selected { client, .. }: Selected) -> ApiResult<Json<Vec<SubredditRemovals>>> {
+    info!("Checking removals of own posts");
+
+    let user = user::me(&client).await?;
+    info!("user {:?}", user);
+
+    Ok(Json(removal_report(&client, &user).await?))
+}
+
+#[utoipa::path(tag = "reports", responses((status = 200, body = RetentionStatus)))]
+#[get("/api/v1/retention")]
+async fn retention(runner: Data<RetentionRunner>) -> ApiResult<Json<RetentionStatus>> {
+    Ok(Json(RetentionStatus {
+        next_run_at: runner.next_run_at(),
+        runs: runner.runs()?,
+    }))
+}
+
+#[utoipa::path(
+    tag = "schedule",
+    params(AccountParams),
+    responses((status = 200, body = Vec<ScheduledPost>), (status = 401, body = ErrorEnvelope))
+)]
+#[get("/api/v1/schedule")]
+async fn list_schedule(Selected { account, .. }: Selected) -> Json<Vec<ScheduledPost>> {
+    Json(account.schedule.list())
+}
+
+/// Moves a pending scheduled post.
+#[utoipa::path(
+    tag = "schedule",
+    params(("id" = String, Path), AccountParams),
+    request_body = RescheduleRequest,
+    responses(
+        (status = 200, body = ScheduledPost),
+        (status = 401, body = ErrorEnvelope),
+        (status = 404, body = ErrorEnvelope),
+    )
+)]
+#[patch("/api/v1/schedule/{id}")]
+async fn reschedule(
+    (path, request, selected): (Path<String>, Json<RescheduleRequest>, Selected),
+) -> ApiResult<Json<ScheduledPost>> {
+    let Selected { account, .. } = selected;
+    let id = path.into_inner();
+    info!("Rescheduling {} to {}", id, request.post_at);
+    account
+        .schedule
+        .reschedule(&id, request.post_at)?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("no pending scheduled post with this id"))
+}
+
+#[utoipa::path(
+    tag = "schedule",
+    params(("id" = String, Path), AccountParams),
+    responses(
+        (status = 200, body = ScheduledPost),
+        (status = 401, body = ErrorEnvelope),
+        (status = 404, body = ErrorEnvelope),
+    )
+)]
+#[delete("/api/v1/schedule/{id}")]
+async fn cancel_scheduled(
+    (path, selected): (Path<String>, Selected),
+) -> ApiResult<Json<ScheduledPost>> {
+    let Selected { account, .. } = selected;
+    let id = path.into_inner();
+    info!("Cancelling {}", id);
+    account
+        .schedule
+        .cancel(&id)?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("no pending scheduled post with this id"))
+}
+
+#[utoipa::path(
+    tag = "history",
+    params(AccountParams),
+    responses((status = 200, body = Vec<Submission>), (status = 401, body = ErrorEnvelope))
+)]
+#[get("/api/v1/history")]
+async fn list_history(Selected { account, .. }: Selected) -> Json<Vec<Submission>> {
+    Json(account.history.list())
+}
+
+#[utoipa::path(
+    tag = "history",
+    params(("id" = String, Path), AccountParams),
+    responses(
+        (status = 200, body = Chart),
+        (status = 401, body = ErrorEnvelope),
+        (status = 404, body = ErrorEnvelope),
+    )
+)]
+#[get("/api/v1/history/{id}/chart")]
+async fn history_chart((path, selected): (Path<String>, Selected)) -> ApiResult<Json<Chart>> {
+    let Selected { account, .. } = selected;
+    account
+        .history
+        .chart(&path.into_inner())
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("no submission with this id"))
+}
+
+#[utoipa::path(
+    tag = "jobs",
+    params(("id" = String, Path)),
+    responses((status = 200, body = Job), (status = 404, body = ErrorEnvelope))
+)]
+#[get("/api/v1/jobs/{id}")]
+async fn show_job((path, jobs): (Path<String>, Data<Jobs>)) -> ApiResult<Json<Job>> {
+    jobs.get(&path.into_inner())
+        .map(Json)
+        .ok_or_else(no_such_job)
+}
+
+/// Asks a running job to stop after its current item.
+#[utoipa::path(
+    tag = "jobs",
+    params(("id" = String, Path)),
+    responses(
+        (status = 202, body = Job),
+        (status = 404, body = ErrorEnvelope),
+        (status = 409, body = ErrorEnvelope, description = "The job is already finished"),
+    )
+)]
+#[delete("/api/v1/jobs/{id}")]
+async fn cancel_job((path, jobs): (Path<String>, Data<Jobs>)) -> ApiResult<HttpResponse> {
+    match jobs.cancel(&path.into_inner()) {
+        Some(job) if job.is_finished() => Err(ApiError::conflict("the job is already finished")),
+        Some(job) => Ok(HttpResponse::Accepted().json(job)),
+        None => Err(no_such_job()),
+    }
+}
+
+/// Server-sent events with the job every time it changed, until it is finished.
+#[utoipa::path(
+    tag = "jobs",
+    params(("id" = String, Path)),
+    responses(
+        (status = 200, body = Job, content_type = "text/event-stream"),
+        (status = 404, body = ErrorEnvelope),
+    )
+)]
+#[get("/api/v1/jobs/{id}/events")]
+async fn job_events((path, jobs): (Path<String>, Data<Jobs>)) -> ApiResult<HttpResponse> {
+    let id = path.into_inner();
+    if jobs.get(&id).is_none() {
+        return Err(no_such_job());
+    }
+    let events = stream::unfold(Some(String::new()), move |last_sent| {
+        let (jobs, id) = (jobs.clone(), id.clone());
+        async move {
+            let last_sent = last_sent?;
+            loop {
+                let job = jobs.get(&id)?;
+                let json = serde_json::to_string(&job).ok()?;
+                if json != last_sent {
+                    let event = Bytes::from(format!("data: {}\n\n", json));
+                    let next = (!job.is_finished()).then_some(json);
+                    return Some((Ok::<_, actix_web::Error>(event), next));
+                }
+                actix_web::rt::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+        }
+    });
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(events))
+}
+
+fn no_such_job() -> ApiError {
+    ApiError::not_found("no job with this id")
+}
+
+fn job_started(job_id: String) -> HttpResponse {
+    HttpResponse::Accepted().json(JobStarted { job_id })
+}
+
+/// Answer to every request the API has no route for.
+pub async fn not_found() -> ApiResult<HttpResponse> {
+    Err(ApiError::not_found("no such endpoint"))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum LoginResponse {
+    /// Open `url` in a browser to finish the login.
+    Pending {
+        id: String,
+        url: String,
+    },
+    LoggedIn {
+        account: String,
+    },
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AccountList {
+    default_account: Option<String>,
+    accounts: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct JobStarted {
+    job_id: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct MatchParams {
+    mode: Option<MatchMode>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DeletePostsParams {
+    #[serde(default = "default_upvotes_below")]
+    #[param(default = 5)]
+    upvotes_below: i64,
+}
+
+fn default_upvotes_below() -> i64 {
+    5
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct FlairsRequest {
+    #[serde(default)]
+    subreddits: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PerformanceParams {
+    #[param(inline)]
+    format: Option<ReportFormat>,
+    /// Defaults to `TIMEZONE`.
+    timezone: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RescheduleRequest {
+    post_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RetentionStatus {
+    next_run_at: Option<DateTime<Utc>>,
+    runs: Vec<RetentionRun>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AccountParams {
+    /// Overrides the `X-Reddit-Account` header and the default account.
+    account: Option<String>,
+}
+
+/// The account a request acts for, named by the `X-Reddit-Account` header or the `account` query
+/// parameter, otherwise the default account.
+pub struct Selected {
+    pub account: Arc<Account>,
+    pub client: AuthRedditClient,
+}
+
+impl FromRequest for Selected {
+    type Error = ApiError;
+    type Future = Ready<ApiResult<Selected>>;
+
+    fn from_request(request: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(select_account(request))
+    }
+}
+
+fn select_account(request: &HttpRequest) -> ApiResult<Selected> {
+    let data = request
+        .app_data::<Data<FileDataStore>>()
+        .expect("data store is registered");
+    let accounts = request
+        .app_data::<Data<Accounts>>()
+        .expect("accounts are registered");
+    let from_header = request
+        .headers()
+        .get(ACCOUNT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from);
+    let from_query = Query::<AccountParams>::from_query(request.query_string())
+        .ok()
+        .and_then(|params| params.into_inner().account);
+    let name = from_query
+        .or(from_header)
+        .or_else(|| data.default_account())
+        .ok_or_else(|| ApiError::unauthorized("not logged in"))?;
+    let auth_token = data
+        .find_auth_token(&name)
+        .ok_or_else(|| ApiError::unauthorized(format!("account {} is not logged in", name)))?;
+    let account = accounts.get(&name)?;
+    Ok(Selected {
+        client: account.client(auth_token),
+        account,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_route_is_described() {
+        let doc = ApiDoc::openapi();
+
+        let mut paths: Vec<&str> = doc.paths.paths.keys().map(String::as_str).collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                "/api/v1/accounts",
+                "/api/v1/comments",
+                "/api/v1/exports",
+                "/api/v1/flairs",
+                "/api/v1/history",
+                "/api/v1/history/{id}/chart",
+                "/api/v1/jobs/{id}",
+                "/api/v1/jobs/{id}/events",
+                "/api/v1/login",
+                "/api/v1/me",
+                "/api/v1/performance",
+                "/api/v1/posts",
+                "/api/v1/posts/files",
+                "/api/v1/posts/files/plan",
+                "/api/v1/posts/plan",
+                "/api/v1/removals",
+                "/api/v1/retention",
+                "/api/v1/schedule",
+                "/api/v1/schedule/{id}",
+                "/api/v1/subreddits",
+                "/api/v1/subreddits/{names}/activity",
+                "/api/v1/subreddits/{names}/comments",
+                "/api/v1/subreddits/{name}/best-time",
+            ]
+        );
+        let schemas = doc.components.unwrap().schemas;
+        assert!(schemas.contains_key("ErrorEnvelope"));
+        assert!(schemas.contains_key("FinalPost"));
+    }
+}
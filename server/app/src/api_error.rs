@@ -0,0 +1,139 @@
+use crate::error::Error;
+use actix_web::error::{JsonPayloadError, PathError, QueryPayloadError};
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use serde_derive::Serialize;
+use std::fmt::{Display, Formatter};
+use utoipa::ToSchema;
+
+/// Body of every failed API response.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ErrorEnvelope {
+    pub error: ErrorBody,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ErrorBody {
+    /// Stable machine readable code, e.g. `not_found` or `invalid_request`.
+    pub code: String,
+    pub message: String,
+    /// Every problem found, e.g. each invalid post of a batch.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<String>,
+}
+
+/// Error of an API handler, answered as an `ErrorEnvelope`.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+    details: Vec<String>,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> ApiError {
+        ApiError {
+            status,
+            code,
+            message: message.into(),
+            details: Vec::new(),
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> ApiError {
+        ApiError::new(StatusCode::BAD_REQUEST, "invalid_request", message)
+    }
+
+    pub fn invalid(message: impl Into<String>, details: Vec<String>) -> ApiError {
+        ApiError {
+            details,
+            ..ApiError::bad_request(message)
+        }
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> ApiError {
+        ApiError::new(StatusCode::UNAUTHORIZED, "unauthorized", message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> ApiError {
+        ApiError::new(StatusCode::NOT_FOUND, "not_found", message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> ApiError {
+        ApiError::new(StatusCode::CONFLICT, "conflict", message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> ApiError {
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", message)
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status).json(ErrorEnvelope {
+            error: ErrorBody {
+                code: String::from(self.code),
+                message: self.message.clone(),
+                details: self.details.clone(),
+            },
+        })
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(error: Error) -> Self {
+        let message = error.to_string();
+        match error {
+            Error::Config(_) | Error::InvalidPattern(_) => ApiError::bad_request(message),
+            Error::Http(_) | Error::Reddit(_) => {
+                ApiError::new(StatusCode::BAD_GATEWAY, "reddit_error", message)
+            }
+            Error::Json(_) | Error::Io(_) | Error::Csv(_) => ApiError::internal(message),
+        }
+    }
+}
+
+pub fn json_error_handler(error: JsonPayloadError, _: &HttpRequest) -> actix_web::Error {
+    ApiError::bad_request(error.to_string()).into()
+}
+
+pub fn query_error_handler(error: QueryPayloadError, _: &HttpRequest) -> actix_web::Error {
+    ApiError::bad_request(error.to_string()).into()
+}
+
+pub fn path_error_handler(error: PathError, _: &HttpRequest) -> actix_web::Error {
+    ApiError::bad_request(error.to_string()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::body::MessageBody;
+
+    #[test]
+    fn errors_share_one_envelope() {
+        let error = ApiError::from(Error::Config(String::from("no posts file")));
+        let response = error.error_response();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().try_into_bytes().unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error"]["code"], "invalid_request");
+        assert_eq!(
+            json["error"]["message"],
+            "configuration error: no posts file"
+        );
+        assert!(json["error"].get("details").is_none());
+    }
+}
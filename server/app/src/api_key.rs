@@ -0,0 +1,133 @@
+use crate::api_error::ApiError;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderMap, HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE};
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use actix_web::ResponseError;
+use uuid::Uuid;
+
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+/// The description of the API tells how to send the key.
+const OPEN_PATHS: [&str; 1] = ["/api/v1/openapi.json"];
+
+/// Whether requests to `path` need the API key, the OAuth callback is outside of `/api` because
+/// reddit redirects the browser there and it can't carry the key.
+pub fn is_guarded(path: &str) -> bool {
+    path.starts_with("/api/") && !OPEN_PATHS.contains(&path)
+}
+
+/// Key the HTTP API asks for, read once on start and registered as app data.
+pub struct ApiKey(String);
+
+impl ApiKey {
+    /// The `API_KEY` of `.env`, or a random key for this run, so the API is never open.
+    pub fn new(configured: Option<String>) -> ApiKey {
+        ApiKey(configured.unwrap_or_else(|| Uuid::new_v4().simple().to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Accepts the key as `Authorization: Bearer {key}` or `X-Api-Key: {key}`.
+pub fn is_authorized(headers: &HeaderMap, api_key: &str) -> bool {
+    let bearer = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let header = headers
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok());
+    [bearer, header]
+        .into_iter()
+        .flatten()
+        .any(|given| constant_time_eq(given.trim().as_bytes(), api_key.as_bytes()))
+}
+
+/// Compares without stopping at the first difference, so the time doesn't tell how much matched.
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    left.len() == right.len()
+        && left
+            .iter()
+            .zip(right)
+            .fold(0, |difference, (l, r)| difference | (l ^ r))
+            == 0
+}
+
+/// Middleware answering `401` to guarded requests without the [`ApiKey`].
+pub async fn require_api_key(
+    api_key: Data<ApiKey>,
+    request: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let authorized =
+        !is_guarded(request.path()) || is_authorized(request.headers(), api_key.as_str());
+    if !authorized {
+        let mut response = ApiError::unauthorized("missing or wrong API key").error_response();
+        response
+            .headers_mut()
+            .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        return Ok(request.into_response(response).map_into_right_body());
+    }
+    next.call(request)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::HeaderName;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{middleware, web, App, HttpResponse};
+
+    #[test]
+    fn only_the_right_key_is_accepted() {
+        let mut headers = HeaderMap::new();
+        assert!(!is_authorized(&headers, "secret"));
+
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+        assert!(is_authorized(&headers, "secret"));
+        assert!(!is_authorized(&headers, "secret2"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HeaderName::from_static("x-api-key"),
+            HeaderValue::from_static("secreT"),
+        );
+        assert!(!is_authorized(&headers, "secret"));
+
+        assert!(is_guarded("/api/v1/posts"));
+        assert!(!is_guarded("/api/v1/openapi.json"));
+        assert!(!is_guarded("/reddit/login-callback"));
+        assert!(!is_guarded("/"));
+    }
+
+    #[actix_web::test]
+    async fn guarded_requests_need_the_key_given_on_start() {
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(ApiKey::new(Some(String::from("secret")))))
+                .wrap(middleware::from_fn(require_api_key))
+                .route("/api/v1/me", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let request = TestRequest::get().uri("/api/v1/me").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let request = TestRequest::get()
+            .uri("/api/v1/me")
+            .insert_header((API_KEY_HEADER, "secret"))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let generated = ApiKey::new(None);
+        assert_eq!(generated.as_str().len(), 32);
+        assert_ne!(generated.as_str(), ApiKey::new(None).as_str());
+    }
+}
@@ -0,0 +1,207 @@
+use crate::best_time::{self, BestTimeReport, SamplingSettings};
+use crate::environment::Environment;
+use crate::error::{Error, Result};
+use crate::post::PlannedPost;
+use crate::reddit_client::AuthRedditClient;
+use crate::schedule::{Schedule, ScheduleStatus};
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use log::info;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+/// How many days ahead a free slot is searched for before giving up.
+const MAX_DAYS_AHEAD: i64 = 60;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ScheduleMode {
+    Auto,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AutoScheduleSettings {
+    #[serde(default = "default_min_gap_minutes")]
+    pub min_gap_minutes: u64,
+    #[serde(default = "default_daily_cap")]
+    pub daily_cap_per_subreddit: usize,
+    #[serde(default = "default_top_slots")]
+    pub top_slots: usize,
+    pub window_days: Option<u32>,
+    /// Defaults to `TIMEZONE`.
+    pub timezone: Option<String>,
+}
+
+impl Default for AutoScheduleSettings {
+    fn default() -> Self {
+        AutoScheduleSettings {
+            min_gap_minutes: default_min_gap_minutes(),
+            daily_cap_per_subreddit: default_daily_cap(),
+            top_slots: default_top_slots(),
+            window_days: None,
+            timezone: None,
+        }
+    }
+}
+
+fn default_min_gap_minutes() -> u64 {
+    60
+}
+
+fn default_daily_cap() -> usize {
+    1
+}
+
+fn default_top_slots() -> usize {
+    5
+}
+
+/// Assigns `post_at` to every planned post without one, using best times of its subreddit.
+pub async fn assign_slots(
+    client: &AuthRedditClient,
+    planned_posts: &mut [PlannedPost],
+    settings: &AutoScheduleSettings,
+    schedule: &Schedule,
+) -> Result<()> {
+    let timezone = settings
+        .timezone
+        .clone()
+        .unwrap_or_else(|| Environment::read_env().timezone);
+    let sampling = SamplingSettings {
+        window_days: settings
+            .window_days
+            .unwrap_or(SamplingSettings::default().window_days),
+        timezone: Some(timezone.clone()),
+        ..SamplingSettings::default()
+    };
+    let mut occupied: Vec<(String, DateTime<Utc>)> = schedule
+        .list()
+        .into_iter()
+        .filter(|scheduled| scheduled.status == ScheduleStatus::Pending)
+        .map(|scheduled| (scheduled.post.subreddit, scheduled.post_at))
+        .collect();
+    occupied.extend(planned_posts.iter().filter_map(|planned| {
+        planned
+            .post_at
+            .map(|at| (planned.post.subreddit.clone(), at))
+    }));
+
+    let mut reports: HashMap<String, BestTimeReport> = HashMap::new();
+    let now = Utc::now();
+    for planned in planned_posts
+        .iter_mut()
+        .filter(|planned| planned.post_at.is_none())
+    {
+        let subreddit = planned.post.subreddit.to_lowercase();
+        if !reports.contains_key(&subreddit) {
+            let report = best_time::best_time_for(client, &subreddit, &sampling, &timezone).await?;
+            reports.insert(subreddit.clone(), report);
+        }
+        let report = &reports[&subreddit];
+        let slot = find_slot(report, &subreddit, &occupied, settings, now).ok_or_else(|| {
+            Error::Config(format!(
+                "no free slot for {} in the next {} days",
+                subreddit, MAX_DAYS_AHEAD
+            ))
+        })?;
+        info!("Auto scheduled post to {} at {}", subreddit, slot);
+        occupied.push((subreddit, slot));
+        planned.post_at = Some(slot);
+    }
+    Ok(())
+}
+
+/// Earliest best slot after `after` keeping `min_gap_minutes` to every occupied slot and
+/// at most `daily_cap_per_subreddit` posts to the same subreddit per day.
+pub fn find_slot(
+    report: &BestTimeReport,
+    subreddit: &str,
+    occupied: &[(String, DateTime<Utc>)],
+    settings: &AutoScheduleSettings,
+    after: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let min_gap = Duration::minutes(settings.min_gap_minutes as i64);
+    let tz = report.tz();
+    let limit = after + Duration::days(MAX_DAYS_AHEAD);
+    let mut cursor = after;
+    while cursor < limit {
+        let candidate = if report.buckets.is_empty() {
+            next_full_hour(cursor)?
+        } else {
+            report.next_slot(cursor, settings.top_slots)?
+        };
+        let too_close = occupied
+            .iter()
+            .any(|(_, at)| (*at - candidate).abs() < min_gap);
+        let day = candidate.with_timezone(&tz).date_naive();
+        let same_day = occupied
+            .iter()
+            .filter(|(occupied_subreddit, at)| {
+                occupied_subreddit.eq_ignore_ascii_case(subreddit)
+                    && at.with_timezone(&tz).date_naive() == day
+            })
+            .count();
+        if !too_close && same_day < settings.daily_cap_per_subreddit {
+            return Some(candidate);
+        }
+        cursor = candidate + Duration::minutes(1);
+    }
+    None
+}
+
+fn next_full_hour(after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let hour = after.duration_trunc(Duration::hours(1)).ok()?;
+    if hour < after {
+        Some(hour + Duration::hours(1))
+    } else {
+        Some(hour)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::post::PostInfo;
+    use crate::test_support::post_info;
+    use chrono::TimeZone;
+    use chrono_tz::Tz;
+
+    fn report(hours: &[u32]) -> BestTimeReport {
+        let posts: Vec<PostInfo> = hours
+            .iter()
+            .enumerate()
+            .map(|(index, hour)| {
+                let created = Utc.with_ymd_and_hms(2023, 6, 5, *hour, 0, 0).unwrap();
+                post_info(&index.to_string(), "art", created, 100 - index as i64)
+            })
+            .collect();
+        BestTimeReport::new("art", &posts, Tz::UTC, 30)
+    }
+
+    #[test]
+    fn slots_respect_gap_and_daily_cap() {
+        // Monday 2023-06-05 is sampled at 10:00 and 11:00
+        let report = report(&[10, 11]);
+        let after = Utc.with_ymd_and_hms(2023, 6, 12, 0, 0, 0).unwrap();
+        let settings = AutoScheduleSettings {
+            min_gap_minutes: 90,
+            ..AutoScheduleSettings::default()
+        };
+        let monday_ten = Utc.with_ymd_and_hms(2023, 6, 12, 10, 0, 0).unwrap();
+
+        assert_eq!(
+            find_slot(&report, "art", &[], &settings, after),
+            Some(monday_ten)
+        );
+        let other = vec![(String::from("pics"), monday_ten)];
+        assert_eq!(
+            find_slot(&report, "art", &other, &settings, after),
+            Some(Utc.with_ymd_and_hms(2023, 6, 19, 10, 0, 0).unwrap())
+        );
+        let same_day = vec![(String::from("Art"), monday_ten - Duration::hours(5))];
+        assert_eq!(
+            find_slot(&report, "art", &same_day, &settings, after),
+            Some(Utc.with_ymd_and_hms(2023, 6, 19, 10, 0, 0).unwrap())
+        );
+    }
+}
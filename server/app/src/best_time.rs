@@ -0,0 +1,247 @@
+use crate::error::{Error, Result};
+use crate::listing::{self, SubredditSort, TimeFilter};
+use crate::post::PostInfo;
+use crate::reddit_client::AuthRedditClient;
+use crate::stats::Distribution;
+use chrono::{DateTime, Datelike, Duration, DurationRound, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use futures::{future, StreamExt, TryStreamExt};
+use log::info;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Deserialize, Debug, Clone, IntoParams)]
+pub struct SamplingSettings {
+    #[serde(default = "default_window_days")]
+    pub window_days: u32,
+    #[serde(default = "default_max_posts")]
+    pub max_posts: usize,
+    pub timezone: Option<String>,
+}
+
+impl Default for SamplingSettings {
+    fn default() -> Self {
+        SamplingSettings {
+            window_days: default_window_days(),
+            max_posts: default_max_posts(),
+            timezone: None,
+        }
+    }
+}
+
+fn default_window_days() -> u32 {
+    30
+}
+
+fn default_max_posts() -> usize {
+    1000
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct Bucket {
+    #[schema(value_type = String, example = "Mon")]
+    pub weekday: Weekday,
+    pub hour: u32,
+    pub score: Distribution,
+    pub comments: Distribution,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct BestTimeReport {
+    pub subreddit: String,
+    pub timezone: String,
+    pub window_days: u32,
+    pub sampled_posts: usize,
+    /// Sorted from the best bucket (highest median score) to the worst one.
+    pub buckets: Vec<Bucket>,
+    #[serde(skip)]
+    tz: Tz,
+}
+
+impl BestTimeReport {
+    pub fn new(subreddit: &str, posts: &[PostInfo], tz: Tz, window_days: u32) -> BestTimeReport {
+        let mut grouped: BTreeMap<(u32, u32), Vec<&PostInfo>> = BTreeMap::new();
+        for post in posts {
+            let created = match DateTime::from_timestamp(post.created_utc as i64, 0) {
+                Some(created) => created.with_timezone(&tz),
+                None => continue,
+            };
+            grouped
+                .entry((created.weekday().num_days_from_monday(), created.hour()))
+                .or_default()
+                .push(post);
+        }
+
+        let mut buckets: Vec<Bucket> = grouped
+            .into_iter()
+            .map(|((weekday, hour), posts)| Bucket {
+                weekday: weekday_from_monday(weekday),
+                hour,
+                score: Distribution::of(posts.iter().map(|post| post.score as f64)),
+                comments: Distribution::of(posts.iter().map(|post| post.num_comments as f64)),
+            })
+            .collect();
+        buckets.sort_by(|a, b| {
+            b.score
+                .median
+                .total_cmp(&a.score.median)
+                .then(b.score.count.cmp(&a.score.count))
+        });
+
+        BestTimeReport {
+            subreddit: String::from(subreddit),
+            timezone: tz.name().to_string(),
+            window_days,
+            sampled_posts: posts.len(),
+            buckets,
+            tz,
+        }
+    }
+
+    pub fn tz(&self) -> Tz {
+        self.tz
+    }
+
+    /// First full hour at or after `after` which falls into one of the `top` best buckets.
+    pub fn next_slot(&self, after: DateTime<Utc>, top: usize) -> Option<DateTime<Utc>> {
+        let best: HashSet<(Weekday, u32)> = self
+            .buckets
+            .iter()
+            .take(top)
+            .map(|bucket| (bucket.weekday, bucket.hour))
+            .collect();
+        let start = after.duration_trunc(Duration::hours(1)).ok()?;
+        let start = if start < after {
+            start + Duration::hours(1)
+        } else {
+            start
+        };
+        (0..24 * 7)
+            .map(|hours| start + Duration::hours(hours))
+            .find(|slot| {
+                let local = slot.with_timezone(&self.tz);
+                best.contains(&(local.weekday(), local.hour()))
+            })
+    }
+}
+
+fn weekday_from_monday(days: u32) -> Weekday {
+    Weekday::try_from(days as u8).expect("weekday index below 7")
+}
+
+pub fn parse_timezone(timezone: &str) -> Result<Tz> {
+    timezone
+        .parse()
+        .map_err(|_| Error::Config(format!("unknown timezone {}", timezone)))
+}
+
+/// Samples posts of the last `window_days` from `/new` and `/top` of the subreddit.
+pub async fn sample(
+    client: &AuthRedditClient,
+    subreddit: &str,
+    settings: &SamplingSettings,
+    now: DateTime<Utc>,
+) -> Result<Vec<PostInfo>> {
+    let since = (now - Duration::days(settings.window_days as i64)).timestamp() as f64;
+
+    let newest: Vec<PostInfo> = listing::subreddit_posts(client, subreddit, SubredditSort::New)
+        .try_take_while(|post| future::ready(Ok(post.created_utc >= since)))
+        .take(settings.max_posts)
+        .try_collect()
+        .await?;
+    let top: Vec<PostInfo> = listing::subreddit_posts(
+        client,
+        subreddit,
+        SubredditSort::Top(time_filter_for(settings.window_days)),
+    )
+    .take(settings.max_posts)
+    .try_filter(|post| future::ready(post.created_utc >= since))
+    .try_collect()
+    .await?;
+    info!(
+        "Sampled {} new and {} top posts from {}",
+        newest.len(),
+        top.len(),
+        subreddit
+    );
+
+    let mut seen = HashSet::new();
+    Ok(newest
+        .into_iter()
+        .chain(top)
+        .filter(|post| seen.insert(post.name.clone()))
+        .collect())
+}
+
+pub async fn best_time_for(
+    client: &AuthRedditClient,
+    subreddit: &str,
+    settings: &SamplingSettings,
+    default_timezone: &str,
+) -> Result<BestTimeReport> {
+    let tz = parse_timezone(settings.timezone.as_deref().unwrap_or(default_timezone))?;
+    let posts = sample(client, subreddit, settings, Utc::now()).await?;
+    Ok(BestTimeReport::new(
+        subreddit,
+        &posts,
+        tz,
+        settings.window_days,
+    ))
+}
+
+fn time_filter_for(window_days: u32) -> TimeFilter {
+    match window_days {
+        0..=1 => TimeFilter::Day,
+        2..=7 => TimeFilter::Week,
+        8..=31 => TimeFilter::Month,
+        32..=365 => TimeFilter::Year,
+        _ => TimeFilter::All,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::post_info;
+    use chrono::TimeZone;
+
+    fn post(id: &str, created: DateTime<Utc>, score: i64) -> PostInfo {
+        post_info(id, "rust", created, score)
+    }
+
+    #[test]
+    fn posts_are_bucketed_in_the_requested_timezone() {
+        let tz: Tz = "Europe/Berlin".parse().unwrap();
+        // Monday 2023-06-05 08:30 UTC is 10:30 in Berlin
+        let monday = Utc.with_ymd_and_hms(2023, 6, 5, 8, 30, 0).unwrap();
+        let posts = vec![
+            post("a", monday, 10),
+            post("b", monday + Duration::days(7), 30),
+            post("c", monday + Duration::hours(5), 2),
+        ];
+
+        let report = BestTimeReport::new("rust", &posts, tz, 30);
+
+        let slots: Vec<(Weekday, u32)> = report
+            .buckets
+            .iter()
+            .map(|bucket| (bucket.weekday, bucket.hour))
+            .collect();
+        assert_eq!(slots, vec![(Weekday::Mon, 10), (Weekday::Mon, 15)]);
+        assert_eq!(report.buckets[0].score.median, 20.0);
+    }
+
+    #[test]
+    fn next_slot_is_the_next_best_hour() {
+        let monday = Utc.with_ymd_and_hms(2023, 6, 5, 8, 30, 0).unwrap();
+        let report = BestTimeReport::new("rust", &[post("a", monday, 10)], Tz::UTC, 30);
+
+        let slot = report.next_slot(monday + Duration::minutes(1), 1);
+
+        assert_eq!(
+            slot,
+            Some(Utc.with_ymd_and_hms(2023, 6, 12, 8, 0, 0).unwrap())
+        );
+    }
+}
@@ -0,0 +1,469 @@
+use clap::{Parser, Subcommand};
+use log::info;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::process::ExitCode;
+use std::time::Duration;
+use subreddit_posts_logic::account::{migrate_legacy, Account, Accounts};
+use subreddit_posts_logic::comment::delete_all_comments;
+use subreddit_posts_logic::cooldown;
+use subreddit_posts_logic::data_store::DataStore;
+use subreddit_posts_logic::environment::{AuthFlow, Environment};
+use subreddit_posts_logic::error::{Error, Result};
+use subreddit_posts_logic::export::export_history;
+use subreddit_posts_logic::file_data_store::FileDataStore;
+use subreddit_posts_logic::flairs::retrieve_flairs_for;
+use subreddit_posts_logic::job::NoProgress;
+use subreddit_posts_logic::login::{
+    auth_token_for, login_url, parse_callback, password_token, request_login, store_login,
+};
+use subreddit_posts_logic::post::{self, delete_with_upvotes_lt, Posts, SubmissionStatus};
+use subreddit_posts_logic::reddit_client::AuthRedditClient;
+use subreddit_posts_logic::subreddit::{
+    self, MatchMode, PageRequest, SortBy, SubredditMatcher, SubredditReport,
+};
+use subreddit_posts_logic::user;
+
+/// Everything went fine.
+const EXIT_OK: u8 = 0;
+/// Reddit or the local files returned an error.
+const EXIT_ERROR: u8 = 1;
+/// There is no login yet, run `login` first.
+const EXIT_NOT_LOGGED_IN: u8 = 3;
+/// Some posts were skipped or failed.
+const EXIT_PARTIAL: u8 = 4;
+
+#[derive(Parser)]
+#[command(about = "Posts to and cleans up subreddits, sharing the login with the server")]
+struct Cli {
+    /// Prints JSON instead of a table.
+    #[arg(long, global = true)]
+    json: bool,
+    /// Reddit account to act for, by default the first one which logged in.
+    #[arg(long, global = true)]
+    account: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Opens the browser and waits until the running server receives the login callback.
+    Login {
+        #[arg(long, default_value_t = 300)]
+        timeout_seconds: u64,
+        /// Prints the login URL and reads the callback URL or code from stdin, no server needed.
+        #[arg(long)]
+        headless: bool,
+    },
+    /// Submits or schedules the posts of the file.
+    Post {
+        #[arg(long, default_value = ".posts")]
+        file: String,
+        /// Only shows what would be posted.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Lists link flairs of the subreddits, by default the ones in `.subreddits`.
+    Flairs { subreddits: Vec<String> },
+    /// Exports the history and deletes own posts or comments.
+    Delete {
+        #[command(subcommand)]
+        target: DeleteTarget,
+    },
+    /// Own activity in subreddits.
+    Sub {
+        #[command(subcommand)]
+        command: SubCommand,
+    },
+    /// Exports own posts and comments to `EXPORT_DIR`.
+    Export,
+}
+
+#[derive(Subcommand)]
+enum DeleteTarget {
+    Posts {
+        #[arg(long, default_value_t = 5)]
+        upvotes_below: i64,
+    },
+    Comments,
+}
+
+#[derive(Subcommand)]
+enum SubCommand {
+    Info {
+        /// Comma separated subreddit names or patterns.
+        sub_names: String,
+        /// `exact`, `glob` or `regex`.
+        #[arg(long, default_value = "exact", value_parser = parse_enum::<MatchMode>)]
+        mode: MatchMode,
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+        #[arg(long, default_value_t = 100)]
+        limit: usize,
+        /// `new`, `old`, `top` or `bottom`.
+        #[arg(long, default_value = "new", value_parser = parse_enum::<SortBy>)]
+        sort: SortBy,
+    },
+}
+
+#[actix_web::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    dotenv::from_filename("server/.env")
+        .or_else(|_| dotenv::from_filename(".env"))
+        .expect(".env file not found");
+    env_logger::init();
+    let env = Environment::read_env();
+    let store = FileDataStore::new(&env.data_dir);
+    migrate_legacy(&env.data_dir, &store).await;
+
+    if let Command::Login {
+        timeout_seconds,
+        headless,
+    } = cli.command
+    {
+        if env.auth_flow == AuthFlow::Password {
+            return match password_login(&env, &store).await {
+                Ok(()) => ExitCode::from(EXIT_OK),
+                Err(error) => {
+                    eprintln!("{}", error);
+                    ExitCode::from(EXIT_NOT_LOGGED_IN)
+                }
+            };
+        }
+        if headless || env.headless {
+            return headless_login(env, &store).await;
+        }
+        return login(env, &store, timeout_seconds).await;
+    }
+    if env.auth_flow == AuthFlow::Password {
+        // tokens of script apps expire after an hour, a fresh one is cheap
+        if let Err(error) = password_login(&env, &store).await {
+            eprintln!("{}", error);
+            return ExitCode::from(EXIT_NOT_LOGGED_IN);
+        }
+    }
+    let name = cli.account.or_else(|| store.default_account());
+    let auth_token = name.as_deref().and_then(|name| store.find_auth_token(name));
+    let (Some(name), Some(auth_token)) = (name, auth_token) else {
+        eprintln!("Not logged in, run `login` first");
+        return ExitCode::from(EXIT_NOT_LOGGED_IN);
+    };
+    let account = match Accounts::new(&env.data_dir).get(&name) {
+        Ok(account) => account,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+    let client = account.client(auth_token);
+    match run(cli.command, cli.json, &client, &account, &env).await {
+        Ok(code) => ExitCode::from(code),
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+async fn login(env: Environment, store: &FileDataStore, timeout_seconds: u64) -> ExitCode {
+    let previous = tokens(store);
+    let login_request_id = request_login(env).await.id;
+    store.store_login_request_id(login_request_id);
+    println!("Waiting for the server to receive the login callback...");
+    for _ in 0..timeout_seconds {
+        actix_web::rt::time::sleep(Duration::from_secs(1)).await;
+        if tokens(store) != previous {
+            println!("Logged in");
+            return ExitCode::from(EXIT_OK);
+        }
+    }
+    eprintln!("No login callback within {} seconds", timeout_seconds);
+    ExitCode::from(EXIT_NOT_LOGGED_IN)
+}
+
+/// Tokens of all accounts, a new login changes one of them.
+fn tokens(store: &FileDataStore) -> Vec<Option<String>> {
+    store
+        .accounts()
+        .iter()
+        .map(|account| store.find_auth_token(account))
+        .collect()
+}
+
+async fn password_login(env: &Environment, store: &FileDataStore) -> Result<()> {
+    let token = password_token(env).await?;
+    store_login(store, token).await?;
+    Ok(())
+}
+
+async fn headless_login(env: Environment, store: &FileDataStore) -> ExitCode {
+    let login_request = login_url(env);
+    println!(
+        "Open this URL in any browser and log in:\n{}",
+        login_request.url
+    );
+    println!("Then paste the URL you were redirected to (or just its code):");
+    let mut input = String::new();
+    if let Err(read_error) = io::stdin().read_line(&mut input) {
+        eprintln!("{}", read_error);
+        return ExitCode::from(EXIT_ERROR);
+    }
+    let code = match parse_callback(&input) {
+        Ok((_, Some(state))) if state != login_request.id => {
+            eprintln!("The pasted URL belongs to another login request");
+            return ExitCode::from(EXIT_NOT_LOGGED_IN);
+        }
+        Ok((code, _)) => code,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::from(EXIT_NOT_LOGGED_IN);
+        }
+    };
+    let account = match auth_token_for(&code, Environment::read_env()).await {
+        Ok(token) => store_login(store, token).await,
+        Err(error) => Err(error),
+    };
+    match account {
+        Ok(account) => {
+            println!("Logged in as {}", account);
+            ExitCode::from(EXIT_OK)
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::from(EXIT_NOT_LOGGED_IN)
+        }
+    }
+}
+
+async fn run(
+    command: Command,
+    json: bool,
+    client: &AuthRedditClient,
+    account: &Account,
+    env: &Environment,
+) -> Result<u8> {
+    match command {
+        Command::Login { .. } => unreachable!("handled before a client exists"),
+        Command::Post { file, dry_run } => {
+            let content = fs::read_to_string(format!("server/{}", file))
+                .or_else(|_| fs::read_to_string(&file))?;
+            let posts: Posts = serde_json::from_str(&content)?;
+            let problems = posts.validate();
+            if !problems.is_empty() {
+                return Err(Error::Config(problems.join(", ")));
+            }
+            let (schedule, history) = (&account.schedule, &account.history);
+            let cooldowns = cooldown::read_policy();
+            if dry_run {
+                let plan = post::plan(posts, client, schedule, history, &cooldowns).await?;
+                let mut rows: Vec<Vec<String>> = plan
+                    .planned
+                    .iter()
+                    .map(|planned| {
+                        let when = planned
+                            .post_at
+                            .map(|at| at.to_rfc3339())
+                            .unwrap_or(String::from("now"));
+                        row([
+                            &planned.post.subreddit,
+                            &planned.post.title,
+                            "planned",
+                            &when,
+                        ])
+                    })
+                    .collect();
+                rows.extend(plan.skipped.iter().map(|result| {
+                    let reason = match &result.status {
+                        SubmissionStatus::Skipped { reason } => reason.as_str(),
+                        _ => "",
+                    };
+                    row([&result.subreddit, &result.title, "skipped", reason])
+                }));
+                print(
+                    json,
+                    &plan,
+                    &["SUBREDDIT", "TITLE", "STATUS", "DETAILS"],
+                    rows,
+                );
+                return Ok(EXIT_OK);
+            }
+            let report =
+                post::post(posts, client, schedule, history, &cooldowns, &NoProgress).await?;
+            let rows = report
+                .iter()
+                .map(|result| {
+                    let (status, details) = match &result.status {
+                        SubmissionStatus::Posted { id } => ("posted", id.clone()),
+                        SubmissionStatus::Scheduled { post_at, .. } => {
+                            ("scheduled", post_at.to_rfc3339())
+                        }
+                        SubmissionStatus::Skipped { reason } => ("skipped", reason.clone()),
+                        SubmissionStatus::Failed => ("failed", String::new()),
+                    };
+                    row([&result.subreddit, &result.title, status, &details])
+                })
+                .collect();
+            print(
+                json,
+                &report,
+                &["SUBREDDIT", "TITLE", "STATUS", "DETAILS"],
+                rows,
+            );
+            let partial = report.iter().any(|result| {
+                matches!(
+                    result.status,
+                    SubmissionStatus::Skipped { .. } | SubmissionStatus::Failed
+                )
+            });
+            Ok(if partial { EXIT_PARTIAL } else { EXIT_OK })
+        }
+        Command::Flairs { subreddits } => {
+            let subreddits = if subreddits.is_empty() {
+                fs::read_to_string("server/.subreddits")
+                    .or_else(|_| fs::read_to_string(".subreddits"))?
+                    .split(", ")
+                    .map(|name| String::from(name.trim()))
+                    .collect()
+            } else {
+                subreddits
+            };
+            let flairs = retrieve_flairs_for(
+                subreddits.iter().map(String::as_str).collect(),
+                client,
+                &NoProgress,
+            )
+            .await;
+            let rows = flairs
+                .iter()
+                .map(|flair| row([&flair.sub_reddit, &flair.text, &flair.id]))
+                .collect();
+            print(json, &flairs, &["SUBREDDIT", "TEXT", "ID"], rows);
+            Ok(EXIT_OK)
+        }
+        Command::Delete { target } => {
+            let user = user::me(client).await?;
+            info!("user {:?}", user);
+            let summary = export_history(client, &user, &env.export_dir).await?;
+            match target {
+                DeleteTarget::Posts { upvotes_below } => {
+                    delete_with_upvotes_lt(client, &user, upvotes_below, &NoProgress).await?
+                }
+                DeleteTarget::Comments => delete_all_comments(client, &user, &NoProgress).await?,
+            }
+            let rows = vec![row([
+                &summary.posts.to_string(),
+                &summary.comments.to_string(),
+                &summary.json_lines_path.display().to_string(),
+            ])];
+            print(json, &summary, &["POSTS", "COMMENTS", "EXPORTED TO"], rows);
+            Ok(EXIT_OK)
+        }
+        Command::Sub {
+            command:
+                SubCommand::Info {
+                    sub_names,
+                    mode,
+                    offset,
+                    limit,
+                    sort,
+                },
+        } => {
+            let sub_names: Vec<String> = sub_names.split(',').map(String::from).collect();
+            let matcher = SubredditMatcher::new(&sub_names, mode)?;
+            let user = user::me(client).await?;
+            let activity = subreddit::get_all_from(client, &user, &matcher).await?;
+            let page = PageRequest {
+                offset,
+                limit,
+                sort,
+            };
+            let report = SubredditReport::new(activity, &page);
+            let mut rows: Vec<Vec<String>> = report
+                .posts
+                .items
+                .iter()
+                .map(|post| {
+                    row([
+                        "post",
+                        &post.subreddit,
+                        &post.score.to_string(),
+                        &post.title,
+                    ])
+                })
+                .collect();
+            rows.extend(report.comments.items.iter().map(|comment| {
+                row([
+                    "comment",
+                    &comment.subreddit,
+                    &comment.score.to_string(),
+                    &comment.body,
+                ])
+            }));
+            print(json, &report, &["KIND", "SUBREDDIT", "SCORE", "TEXT"], rows);
+            Ok(EXIT_OK)
+        }
+        Command::Export => {
+            let user = user::me(client).await?;
+            let summary = export_history(client, &user, &env.export_dir).await?;
+            let rows = vec![row([
+                &summary.posts.to_string(),
+                &summary.comments.to_string(),
+                &summary.json_lines_path.display().to_string(),
+                &summary.csv_path.display().to_string(),
+            ])];
+            print(
+                json,
+                &summary,
+                &["POSTS", "COMMENTS", "JSON LINES", "CSV"],
+                rows,
+            );
+            Ok(EXIT_OK)
+        }
+    }
+}
+
+fn parse_enum<T: DeserializeOwned>(value: &str) -> std::result::Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_lowercase()))
+        .map_err(|error| error.to_string())
+}
+
+fn row<const N: usize>(cells: [&str; N]) -> Vec<String> {
+    cells.iter().map(|cell| cell.replace('\n', " ")).collect()
+}
+
+fn print<T: Serialize>(json: bool, value: &T, headers: &[&str], rows: Vec<Vec<String>>) {
+    if json {
+        match serde_json::to_string_pretty(value) {
+            Ok(json) => println!("{}", json),
+            Err(error) => eprintln!("{}", Error::from(error)),
+        }
+        return;
+    }
+    let widths: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(index, header)| {
+            rows.iter()
+                .map(|row| row[index].chars().count())
+                .chain([header.len()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let line = |cells: Vec<&str>| {
+        let cells: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        println!("{}", cells.join("  ").trim_end());
+    };
+    line(headers.to_vec());
+    for row in &rows {
+        line(row.iter().map(String::as_str).collect());
+    }
+}
@@ -1,9 +1,13 @@
+use crate::error::Result;
+use crate::job::{ItemStatus, Progress};
+use crate::listing::{self, UserListing};
 use crate::reddit_client::{AuthRedditClient, DeleteRequest};
 use crate::user::User;
 use crate::OAUTH_REDDIT_URL;
-use log::{debug, info};
+use futures::{future, TryStreamExt};
+use log::info;
 use serde_derive::{Deserialize, Serialize};
-use serde_json::Value;
+use utoipa::ToSchema;
 
 #[derive(Serialize, Deserialize, Debug)]
 struct PostComment {
@@ -12,13 +16,22 @@ struct PostComment {
     thing_id: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct Comment {
     pub id: String,
     pub name: String,
     pub body: String,
-    pub upvotes: u64,
+    #[serde(alias = "ups")]
+    pub upvotes: i64,
     pub subreddit: String,
+    #[serde(default)]
+    pub permalink: String,
+    pub link_title: Option<String>,
+    #[serde(default)]
+    pub score: i64,
+    /// Required, retention deletes by age and must not take a missing time for 1970.
+    pub created_utc: f64,
+    pub author_flair_text: Option<String>,
 }
 
 pub async fn submit_comment(client: &AuthRedditClient, comment: String, post_id: String) {
@@ -48,104 +61,51 @@ pub async fn submit_comment(client: &AuthRedditClient, comment: String, post_id:
     client.post(url.as_str(), Some(&comment)).await;
 }
 
-pub async fn delete_all_comments(client: &AuthRedditClient, user: &User) {
-    let comments = retrieve_comments(client, user).await;
+pub async fn delete_all_comments(
+    client: &AuthRedditClient,
+    user: &User,
+    progress: &impl Progress,
+) -> Result<()> {
+    let comments = retrieve_all_with(client, user, |_| true).await?;
     info!("Comments {:?}", comments);
 
-    for comment in comments {
-        let delete_request = DeleteRequest::new_json(comment.name.as_str());
-        client.delete(&delete_request).await;
-    }
-}
-
-async fn retrieve_comments(client: &AuthRedditClient, user: &User) -> Vec<Comment> {
-    let url = format!(
-        "{}{}comments?limit=1000",
-        OAUTH_REDDIT_URL,
-        user.url.as_str()
-    );
-    let body = client.get(url.as_str()).await;
-
-    let value: Value = serde_json::from_str(body.as_str()).expect("Json format expected");
-    debug!("The json value is {}", value);
-    let comments = value["data"]["children"].as_array().unwrap();
-
-    let comments: Vec<Comment> = comments
-        .iter()
-        .map(|comment| Comment {
-            id: String::from(comment["data"]["id"].as_str().unwrap()),
-            name: String::from(comment["data"]["name"].as_str().unwrap()),
-            body: String::from(comment["data"]["body"].as_str().unwrap()),
-            upvotes: comment["data"]["ups"].as_u64().unwrap(),
-            subreddit: String::from(comment["data"]["subreddit"].as_str().unwrap()),
-        })
-        .collect();
-    comments
+    delete_comments(client, &comments, progress).await;
+    Ok(())
 }
 
-pub async fn retrieve_all_with(
+/// Deletes the comments one by one until `progress` is cancelled.
+pub async fn delete_comments(
     client: &AuthRedditClient,
-    user: &User,
-    filter: impl Fn(&Comment) -> bool,
-) -> Vec<Comment> {
-    let mut after: Option<String> = None;
-    let mut result: Vec<Comment> = Vec::new();
-    loop {
-        let (comments, new_after) = retrieve_max(client, user, after.clone()).await;
-        info!("Retrieved {} comments", comments.len());
-
-        after = new_after;
-        if comments.len().eq(&0) {
-            break;
-        }
-        for comment in comments {
-            if after.is_some() {
-                let value = after.clone().unwrap();
-                if value.eq(&comment.name) {
-                    continue;
-                }
-            }
-            if filter(&comment) {
-                result.push(comment);
-            }
-        }
-
-        if after.is_none() {
-            break;
+    comments: &[Comment],
+    progress: &impl Progress,
+) {
+    let first = progress.add_items(
+        comments
+            .iter()
+            .map(|comment| comment.name.clone())
+            .collect(),
+    );
+    for (index, comment) in comments.iter().enumerate() {
+        if progress.is_cancelled() {
+            info!("Deleting comments cancelled");
+            return;
         }
+        let delete_request = DeleteRequest::new_json(comment.name.as_str());
+        info!("Will delete comment {:?}", comment);
+        client.delete(&delete_request).await;
+        progress.finish_item(first + index, ItemStatus::Done, None);
     }
-    return result;
 }
 
-async fn retrieve_max(
+pub async fn retrieve_all_with(
     client: &AuthRedditClient,
     user: &User,
-    after: Option<String>,
-) -> (Vec<Comment>, Option<String>) {
-    let mut url = format!(
-        "{}{}comments?limit=1000",
-        OAUTH_REDDIT_URL,
-        user.url.as_str()
-    );
-    if after.is_some() {
-        url = format!("{}&after={}", url, after.unwrap());
-    }
-    let body = client.get(url.as_str()).await;
-    let value: Value = serde_json::from_str(body.as_str()).expect("Json format expected");
-    debug!("The json value is {}", value);
-    let after = value["data"]["after"].as_str();
-    let comments = value["data"]["children"].as_array().unwrap();
-
-    let result = comments
-        .iter()
-        .map(|comment| Comment {
-            id: String::from(comment["data"]["id"].as_str().unwrap()),
-            name: String::from(comment["data"]["name"].as_str().unwrap()),
-            body: String::from(comment["data"]["body"].as_str().unwrap()),
-            upvotes: comment["data"]["ups"].as_u64().unwrap(),
-            subreddit: String::from(comment["data"]["subreddit"].as_str().unwrap()),
-        })
-        .collect();
-
-    (result, after.map(|val| String::from(val)))
+    filter: impl Fn(&Comment) -> bool,
+) -> Result<Vec<Comment>> {
+    let comments: Vec<Comment> = listing::paginate(client, UserListing::Comments.url(user))
+        .try_filter(|comment| future::ready(filter(comment)))
+        .try_collect()
+        .await?;
+    info!("Retrieved {} comments", comments.len());
+    Ok(comments)
 }
@@ -0,0 +1,95 @@
+use crate::duplicate;
+use crate::history::Submission;
+use crate::subreddit::SubredditMatcher;
+use chrono::{DateTime, Duration, Utc};
+use log::error;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+
+const POLICY_FILE: &str = ".cooldowns";
+
+/// Days to wait before posting the same URL to a subreddit again, read from `.cooldowns`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CooldownPolicy {
+    pub default_days: Option<u32>,
+    #[serde(default)]
+    pub subreddits: HashMap<String, u32>,
+}
+
+impl CooldownPolicy {
+    pub fn days_for(&self, subreddit: &str) -> Option<u32> {
+        self.subreddits
+            .iter()
+            .find(|(name, _)| SubredditMatcher::exact(&[(*name).clone()]).matches(subreddit))
+            .map(|(_, days)| *days)
+            .or(self.default_days)
+    }
+}
+
+pub fn read_policy() -> CooldownPolicy {
+    let content = match fs::read_to_string(format!("server/{}", POLICY_FILE))
+        .or_else(|_| fs::read_to_string(POLICY_FILE))
+    {
+        Ok(content) => content,
+        Err(_) => return CooldownPolicy::default(),
+    };
+    serde_json::from_str(&content).unwrap_or_else(|parse_error| {
+        error!("Cooldown policy is not well-formatted: {}", parse_error);
+        CooldownPolicy::default()
+    })
+}
+
+/// Reason to skip posting `url` to `subreddit` at `post_at`, when our last submission of it
+/// is less than `cooldown_days` old.
+pub fn check(
+    submissions: &[Submission],
+    subreddit: &str,
+    url: &str,
+    cooldown_days: u32,
+    post_at: DateTime<Utc>,
+) -> Option<String> {
+    let last = submissions
+        .iter()
+        .filter(|submission| {
+            submission.subreddit.eq_ignore_ascii_case(subreddit)
+                && duplicate::same_url(&submission.url, url)
+        })
+        .map(|submission| submission.posted_at)
+        .max()?;
+    let allowed_at = last + Duration::days(cooldown_days as i64);
+    if post_at < allowed_at {
+        Some(format!(
+            "{} is in a {} days cooldown since {}, allowed again at {}",
+            subreddit, cooldown_days, last, allowed_at
+        ))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::submission;
+    use chrono::TimeZone;
+
+    #[test]
+    fn reposts_inside_the_cooldown_are_refused() {
+        let policy: CooldownPolicy = serde_json::from_value(serde_json::json!({
+            "default_days": 7, "subreddits": {"r/Art": 30}
+        }))
+        .unwrap();
+        let posted_at = Utc.with_ymd_and_hms(2023, 6, 5, 8, 0, 0).unwrap();
+        let submissions = vec![submission("abc", "art", "https://example.com", posted_at)];
+
+        assert_eq!(policy.days_for("art"), Some(30));
+        assert_eq!(policy.days_for("pics"), Some(7));
+        let url = "https://example.com";
+        assert!(check(&submissions, "art", url, 30, posted_at + Duration::days(29)).is_some());
+        assert!(check(&submissions, "art", url, 30, posted_at + Duration::days(30)).is_none());
+        assert!(check(&submissions, "pics", url, 30, posted_at).is_none());
+        let slashed = "https://example.com/";
+        assert!(check(&submissions, "art", slashed, 30, posted_at).is_some());
+    }
+}
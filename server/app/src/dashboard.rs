@@ -0,0 +1,56 @@
+use actix_web::http::header::ContentType;
+use actix_web::{get, web, HttpResponse, Responder};
+
+/// The dashboard is compiled into the binary, so it works wherever the server is started from.
+const INDEX_HTML: &str = include_str!("../static/index.html");
+const DASHBOARD_JS: &str = include_str!("../static/dashboard.js");
+const DASHBOARD_CSS: &str = include_str!("../static/dashboard.css");
+
+pub fn configure(config: &mut web::ServiceConfig) {
+    config.service(index).service(script).service(style);
+}
+
+#[get("/")]
+async fn index() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(INDEX_HTML)
+}
+
+#[get("/dashboard.js")]
+async fn script() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/javascript; charset=utf-8")
+        .body(DASHBOARD_JS)
+}
+
+#[get("/dashboard.css")]
+async fn style() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/css; charset=utf-8")
+        .body(DASHBOARD_CSS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App};
+
+    #[actix_web::test]
+    async fn dashboard_files_are_served() {
+        let app = test::init_service(App::new().configure(configure)).await;
+
+        for (path, content_type) in [
+            ("/", "text/html; charset=utf-8"),
+            ("/dashboard.js", "text/javascript; charset=utf-8"),
+        ] {
+            let response =
+                test::call_service(&app, test::TestRequest::get().uri(path).to_request()).await;
+            assert!(response.status().is_success(), "{}", path);
+            assert_eq!(
+                response.headers().get("content-type").unwrap(),
+                content_type
+            );
+        }
+    }
+}
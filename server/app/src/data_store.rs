@@ -1,9 +1,55 @@
+use chrono::{DateTime, Duration, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+/// How long a login request waits for its callback.
+pub const LOGIN_REQUEST_MINUTES: i64 = 10;
+
 pub trait DataStore {
+    /// Adds a pending login request, several may be in flight at once.
     fn store_login_request_id(&self, login_request_id: String);
 
-    fn retrieve_login_request_id(&self) -> String;
+    /// Removes the pending login request, `false` when it is unknown or expired.
+    fn take_login_request_id(&self, login_request_id: &str) -> bool;
+
+    /// Stores the token of the reddit account, the first account becomes the default one.
+    fn store_auth_token(&self, account: &str, auth_token: String);
+
+    fn retrieve_auth_token(&self, account: &str) -> String;
 
-    fn store_auth_token(&self, auth_token: String);
+    fn find_auth_token(&self, account: &str) -> Option<String>;
+
+    /// Names of the accounts with a token.
+    fn accounts(&self) -> Vec<String>;
+
+    fn default_account(&self) -> Option<String>;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingLogin {
+    pub id: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl PendingLogin {
+    pub fn new(id: String, now: DateTime<Utc>) -> PendingLogin {
+        PendingLogin {
+            id,
+            expires_at: now + Duration::minutes(LOGIN_REQUEST_MINUTES),
+        }
+    }
+}
+
+/// Drops expired requests and takes the one with `id`, `true` when it was still pending.
+pub fn take_pending(pending: &mut Vec<PendingLogin>, id: &str, now: DateTime<Utc>) -> bool {
+    pending.retain(|login| login.expires_at > now);
+    let before = pending.len();
+    pending.retain(|login| login.id != id);
+    pending.len() < before
+}
 
-    fn retrieve_auth_token(&self) -> String;
+/// Reddit usernames are case-insensitive, so `account` uses the key of an already stored account.
+pub fn account_key<'a>(mut keys: impl Iterator<Item = &'a String>, account: &str) -> String {
+    keys.find(|key| key.eq_ignore_ascii_case(account))
+        .cloned()
+        .unwrap_or_else(|| account.to_string())
 }
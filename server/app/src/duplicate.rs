@@ -0,0 +1,180 @@
+use crate::error::{Error, Result};
+use crate::history::Submission;
+use crate::listing::{Listing, Thing};
+use crate::post::{FinalPost, PostInfo};
+use crate::reddit_client::AuthRedditClient;
+use crate::OAUTH_REDDIT_URL;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use reqwest::Url;
+use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateAction {
+    /// Doesn't submit the post.
+    #[default]
+    Skip,
+    /// Submits the post anyway, with `resubmit` set so reddit accepts it.
+    Warn,
+}
+
+/// How far back a submission of the same URL to the same subreddit counts as a duplicate.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct DuplicateSettings {
+    #[serde(default = "default_window_days")]
+    pub window_days: u32,
+    #[serde(default)]
+    pub action: DuplicateAction,
+}
+
+impl Default for DuplicateSettings {
+    fn default() -> Self {
+        DuplicateSettings {
+            window_days: default_window_days(),
+            action: DuplicateAction::default(),
+        }
+    }
+}
+
+fn default_window_days() -> u32 {
+    30
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateSource {
+    /// Our own submission recorded in the history.
+    History,
+    /// Anyone's submission known to reddit.
+    Reddit,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Duplicate {
+    pub source: DuplicateSource,
+    pub id: String,
+    pub subreddit: String,
+    pub posted_at: DateTime<Utc>,
+}
+
+impl std::fmt::Display for Duplicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let source = match self.source {
+            DuplicateSource::History => "by us",
+            DuplicateSource::Reddit => "by someone",
+        };
+        write!(
+            f,
+            "same URL was posted to {} {} at {} ({})",
+            self.subreddit, source, self.posted_at, self.id
+        )
+    }
+}
+
+/// Latest submission of the post's URL to its subreddit since `since`, first from our history,
+/// then from reddit. Submissions in `own_ids`, the earlier occurrences of a recurring post, don't
+/// count.
+pub async fn find_duplicate(
+    client: &AuthRedditClient,
+    submissions: &[Submission],
+    post: &FinalPost,
+    settings: &DuplicateSettings,
+    own_ids: &[String],
+) -> Result<Option<Duplicate>> {
+    let since = Utc::now() - Duration::days(settings.window_days as i64);
+    if let Some(duplicate) = find_in_history(submissions, post, since, own_ids) {
+        return Ok(Some(duplicate));
+    }
+    let url = Url::parse_with_params(
+        format!("{}/api/info", OAUTH_REDDIT_URL).as_str(),
+        &[("url", post.url.as_str()), ("limit", "100")],
+    )
+    .map_err(|error| Error::Reddit(error.to_string()))?;
+    let listing: Thing<Listing<Thing<PostInfo>>> = client.get_json(url.as_str()).await?;
+    let infos: Vec<PostInfo> = listing.data.children.into_iter().map(|t| t.data).collect();
+    Ok(find_in_reddit(&infos, post, since, own_ids))
+}
+
+pub fn find_in_history(
+    submissions: &[Submission],
+    post: &FinalPost,
+    since: DateTime<Utc>,
+    own_ids: &[String],
+) -> Option<Duplicate> {
+    submissions
+        .iter()
+        .filter(|submission| {
+            !own_ids.contains(&submission.id)
+                && submission.subreddit.eq_ignore_ascii_case(&post.subreddit)
+                && same_url(&submission.url, &post.url)
+                && submission.posted_at >= since
+        })
+        .max_by_key(|submission| submission.posted_at)
+        .map(|submission| Duplicate {
+            source: DuplicateSource::History,
+            id: submission.id.clone(),
+            subreddit: submission.subreddit.clone(),
+            posted_at: submission.posted_at,
+        })
+}
+
+fn find_in_reddit(
+    infos: &[PostInfo],
+    post: &FinalPost,
+    since: DateTime<Utc>,
+    own_ids: &[String],
+) -> Option<Duplicate> {
+    infos
+        .iter()
+        .filter(|info| {
+            !own_ids.contains(&info.id) && info.subreddit.eq_ignore_ascii_case(&post.subreddit)
+        })
+        .filter_map(|info| {
+            let posted_at = Utc.timestamp_opt(info.created_utc as i64, 0).single()?;
+            Some(Duplicate {
+                source: DuplicateSource::Reddit,
+                id: info.id.clone(),
+                subreddit: info.subreddit.clone(),
+                posted_at,
+            })
+        })
+        .filter(|duplicate| duplicate.posted_at >= since)
+        .max_by_key(|duplicate| duplicate.posted_at)
+}
+
+/// URLs are the same with or without a trailing `/`.
+pub fn same_url(left: &str, right: &str) -> bool {
+    left.trim_end_matches('/') == right.trim_end_matches('/')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{final_post, submission};
+
+    #[test]
+    fn only_recent_submissions_to_the_same_subreddit_are_duplicates() {
+        let post = final_post("rust", "https://example.com/");
+        let now = Utc.with_ymd_and_hms(2023, 6, 5, 8, 0, 0).unwrap();
+        let submission = |id: &str, subreddit: &str, days_ago: i64| {
+            submission(
+                id,
+                subreddit,
+                "https://example.com",
+                now - Duration::days(days_ago),
+            )
+        };
+        let submissions = vec![
+            submission("old", "rust", 40),
+            submission("other", "pics", 1),
+            submission("recent", "Rust", 10),
+        ];
+
+        let duplicate =
+            find_in_history(&submissions, &post, now - Duration::days(30), &[]).unwrap();
+        assert_eq!(duplicate.id, "recent");
+        assert_eq!(duplicate.source, DuplicateSource::History);
+        assert!(find_in_history(&submissions, &post, now - Duration::days(5), &[]).is_none());
+    }
+}
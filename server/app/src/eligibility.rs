@@ -0,0 +1,148 @@
+use crate::error::Result;
+use crate::listing::Thing;
+use crate::reddit_client::AuthRedditClient;
+use crate::user::User;
+use crate::OAUTH_REDDIT_URL;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Account requirements of a subreddit which reddit doesn't expose, copied from its rules.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct Requirements {
+    pub min_karma: Option<i64>,
+    pub min_account_age_days: Option<u32>,
+    #[serde(default)]
+    pub verified_email: bool,
+}
+
+/// Part of `/r/{sub}/about` telling whether we may submit there.
+#[derive(Deserialize, Debug, Default)]
+pub struct SubredditAbout {
+    pub display_name: String,
+    /// `public`, `restricted`, `private`, `archived`, ...
+    #[serde(default)]
+    pub subreddit_type: String,
+    /// `any`, `link` or `self`.
+    #[serde(default)]
+    pub submission_type: String,
+    pub user_is_banned: Option<bool>,
+    pub user_is_contributor: Option<bool>,
+    pub user_is_moderator: Option<bool>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Ineligible {
+    pub subreddit: String,
+    pub reasons: Vec<String>,
+}
+
+pub async fn about(client: &AuthRedditClient, subreddit: &str) -> Result<SubredditAbout> {
+    let url = format!("{}/r/{}/about", OAUTH_REDDIT_URL, subreddit);
+    let about: Thing<SubredditAbout> = client.get_json(url.as_str()).await?;
+    Ok(about.data)
+}
+
+/// Reasons why `user` can't submit a post of `kind` to the subreddit, empty when it can.
+pub fn check(
+    about: &SubredditAbout,
+    user: &User,
+    kind: &str,
+    requirements: &Requirements,
+    now: DateTime<Utc>,
+) -> Vec<String> {
+    let mut reasons = Vec::new();
+    let approved =
+        about.user_is_contributor.unwrap_or(false) || about.user_is_moderator.unwrap_or(false);
+    if user.is_suspended {
+        reasons.push(String::from("account is suspended"));
+    }
+    if about.user_is_banned.unwrap_or(false) {
+        reasons.push(String::from("account is banned"));
+    }
+    match about.subreddit_type.as_str() {
+        "restricted" | "private" if !approved => reasons.push(format!(
+            "subreddit is {} and the account is not an approved submitter",
+            about.subreddit_type
+        )),
+        "archived" | "employees_only" | "gold_only" => {
+            reasons.push(format!("subreddit is {}", about.subreddit_type))
+        }
+        _ => {}
+    }
+    match (about.submission_type.as_str(), kind) {
+        ("self", "link") => reasons.push(String::from("subreddit allows text posts only")),
+        ("link", "self") => reasons.push(String::from("subreddit allows link posts only")),
+        _ => {}
+    }
+    let karma = user.link_karma + user.comment_karma;
+    if let Some(min_karma) = requirements.min_karma.filter(|min| karma < *min) {
+        reasons.push(format!("karma {} is below {}", karma, min_karma));
+    }
+    let created = Utc
+        .timestamp_opt(user.created_utc as i64, 0)
+        .single()
+        .unwrap_or(now);
+    let age_days = (now - created).num_days();
+    if let Some(min_days) = requirements
+        .min_account_age_days
+        .filter(|min| created + Duration::days(*min as i64) > now)
+    {
+        reasons.push(format!(
+            "account is {} days old, {} required",
+            age_days, min_days
+        ));
+    }
+    if requirements.verified_email && !user.has_verified_email {
+        reasons.push(String::from("email is not verified"));
+    }
+    reasons
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ineligible_subreddits_have_reasons() {
+        let now = Utc.with_ymd_and_hms(2023, 6, 5, 8, 0, 0).unwrap();
+        let user: User = serde_json::from_value(serde_json::json!({
+            "id": "u", "name": "me", "link_karma": 40,
+            "comment_karma": 10, "created_utc": (now - Duration::days(3)).timestamp() as f64,
+            "has_verified_email": false
+        }))
+        .unwrap();
+        let public: SubredditAbout = serde_json::from_value(serde_json::json!({
+            "display_name": "art", "subreddit_type": "public", "submission_type": "any"
+        }))
+        .unwrap();
+        let restricted: SubredditAbout = serde_json::from_value(serde_json::json!({
+            "display_name": "mods", "subreddit_type": "restricted", "submission_type": "self",
+            "user_is_banned": true, "user_is_contributor": false
+        }))
+        .unwrap();
+        let requirements = Requirements {
+            min_karma: Some(100),
+            min_account_age_days: Some(7),
+            verified_email: true,
+        };
+
+        assert!(check(&public, &user, "link", &Requirements::default(), now).is_empty());
+        assert_eq!(
+            check(&public, &user, "link", &requirements, now),
+            vec![
+                "karma 50 is below 100",
+                "account is 3 days old, 7 required",
+                "email is not verified"
+            ]
+        );
+        assert_eq!(
+            check(&restricted, &user, "link", &Requirements::default(), now),
+            vec![
+                "account is banned",
+                "subreddit is restricted and the account is not an approved submitter",
+                "subreddit allows text posts only"
+            ]
+        );
+    }
+}
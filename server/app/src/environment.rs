@@ -1,23 +1,119 @@
-#[derive(Debug)]
+/// OAuth flow of the reddit app, `code` for "web app", `password` for "script" and `installed`
+/// for "installed app" which has no secret.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AuthFlow {
+    #[default]
+    Code,
+    Password,
+    Installed,
+}
+
+impl std::str::FromStr for AuthFlow {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "code" => Ok(AuthFlow::Code),
+            "password" => Ok(AuthFlow::Password),
+            "installed" => Ok(AuthFlow::Installed),
+            _ => Err(format!("unknown auth flow {}", value)),
+        }
+    }
+}
+
 pub struct Environment {
     pub application_id: String,
     pub application_secret: String,
     pub application_redirection_link: String,
     pub application_scope: String,
+    pub export_dir: String,
+    pub data_dir: String,
+    pub timezone: String,
+    /// Never opens a browser, the login URL is returned or printed instead.
+    pub headless: bool,
+    pub auth_flow: AuthFlow,
+    /// Account of a script app, only used by the password flow.
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Key the HTTP API asks for, one is made up for the run when it is not set.
+    pub api_key: Option<String>,
+    pub bind_address: String,
+    /// Posts files `/api/v1/posts/files` submits, comma separated in `POSTS_FILES`.
+    pub posts_files: Vec<String>,
+    pub port: u16,
+}
+
+/// Logged on start, so the secrets only tell whether they are set.
+impl std::fmt::Debug for Environment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let redacted = |value: Option<&String>| value.map(|_| "<redacted>");
+        f.debug_struct("Environment")
+            .field("application_id", &self.application_id)
+            .field("application_secret", &"<redacted>")
+            .field(
+                "application_redirection_link",
+                &self.application_redirection_link,
+            )
+            .field("application_scope", &self.application_scope)
+            .field("export_dir", &self.export_dir)
+            .field("data_dir", &self.data_dir)
+            .field("timezone", &self.timezone)
+            .field("headless", &self.headless)
+            .field("auth_flow", &self.auth_flow)
+            .field("username", &self.username)
+            .field("password", &redacted(self.password.as_ref()))
+            .field("api_key", &redacted(self.api_key.as_ref()))
+            .field("bind_address", &self.bind_address)
+            .field("posts_files", &self.posts_files)
+            .field("port", &self.port)
+            .finish()
+    }
 }
 
 impl Environment {
     pub fn read_env() -> Environment {
         let application_id = Environment::read_env_property("APPLICATION_ID");
-        let application_secret = Environment::read_env_property("APPLICATION_SECRET");
+        let auth_flow = Environment::read_env_property_or("AUTH_FLOW", AuthFlow::default());
+        let application_secret = match auth_flow {
+            AuthFlow::Installed => String::new(),
+            _ => Environment::read_env_property("APPLICATION_SECRET"),
+        };
         let application_redirection_link =
             Environment::read_env_property("APPLICATION_REDIRECTION_LINK");
         let application_scope = Environment::read_env_property("APPLICATION_SCOPE");
+        let export_dir = Environment::read_env_property_or("EXPORT_DIR", String::from("exports"));
+        let data_dir = Environment::read_env_property_or("DATA_DIR", String::from("data"));
+        let timezone = Environment::read_env_property_or("TIMEZONE", String::from("UTC"));
+        let headless = Environment::read_env_property_or("HEADLESS", false);
+        let username = std::env::var("REDDIT_USERNAME").ok();
+        let password = std::env::var("REDDIT_PASSWORD").ok();
+        let api_key = std::env::var("API_KEY")
+            .ok()
+            .filter(|api_key| !api_key.is_empty());
+        let bind_address =
+            Environment::read_env_property_or("BIND_ADDRESS", String::from("127.0.0.1"));
+        let port = Environment::read_env_property_or("PORT", 9090);
+        let posts_files = Environment::read_env_property_or("POSTS_FILES", String::from(".posts"))
+            .split(',')
+            .map(|file| String::from(file.trim()))
+            .filter(|file| !file.is_empty())
+            .collect();
         Environment {
             application_id,
             application_secret,
             application_redirection_link,
             application_scope,
+            export_dir,
+            data_dir,
+            timezone,
+            headless,
+            auth_flow,
+            username,
+            password,
+            api_key,
+            bind_address,
+            posts_files,
+            port,
         }
     }
 
@@ -33,4 +129,48 @@ impl Environment {
             .parse()
             .unwrap_or_else(|_| panic!("Can't parse property {} of {}", value, name))
     }
+
+    fn read_env_property_or<T>(name: &str, default: T) -> T
+    where
+        T: std::str::FromStr,
+        <T as std::str::FromStr>::Err: std::fmt::Debug,
+    {
+        match std::env::var(name) {
+            Ok(_) => Environment::read_env_property(name),
+            Err(_) => default,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_are_not_logged() {
+        let env = Environment {
+            application_id: String::from("id"),
+            application_secret: String::from("app-secret"),
+            application_redirection_link: String::new(),
+            application_scope: String::new(),
+            export_dir: String::new(),
+            data_dir: String::new(),
+            timezone: String::new(),
+            headless: false,
+            auth_flow: AuthFlow::Password,
+            username: Some(String::from("me")),
+            password: Some(String::from("hunter2")),
+            api_key: Some(String::from("api-secret")),
+            bind_address: String::new(),
+            posts_files: Vec::new(),
+            port: 9090,
+        };
+
+        let logged = format!("{:?}", env);
+        assert!(logged.contains("username: Some(\"me\")"));
+        assert!(logged.contains("password: Some(\"<redacted>\")"));
+        assert!(!logged.contains("app-secret"));
+        assert!(!logged.contains("hunter2"));
+        assert!(!logged.contains("api-secret"));
+    }
 }
@@ -0,0 +1,54 @@
+use std::fmt::{Display, Formatter};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    Http(reqwest::Error),
+    Json(serde_json::Error),
+    Io(std::io::Error),
+    Csv(csv::Error),
+    Reddit(String),
+    InvalidPattern(String),
+    Config(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Http(error) => write!(f, "http error: {}", error),
+            Error::Json(error) => write!(f, "json error: {}", error),
+            Error::Io(error) => write!(f, "io error: {}", error),
+            Error::Csv(error) => write!(f, "csv error: {}", error),
+            Error::Reddit(message) => write!(f, "reddit error: {}", message),
+            Error::InvalidPattern(message) => write!(f, "invalid pattern: {}", message),
+            Error::Config(message) => write!(f, "configuration error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        Error::Http(error)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Json(error)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(error: csv::Error) -> Self {
+        Error::Csv(error)
+    }
+}
@@ -0,0 +1,176 @@
+use crate::comment::{self, Comment};
+use crate::error::Result;
+use crate::post::{self, PostInfo};
+use crate::reddit_client::AuthRedditClient;
+use crate::user::User;
+use crate::REDDIT_URL;
+use log::info;
+use serde_derive::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HistoryRecord {
+    pub kind: String,
+    pub name: String,
+    pub permalink: String,
+    pub title: Option<String>,
+    pub body: String,
+    pub score: i64,
+    pub created_utc: f64,
+    pub subreddit: String,
+    pub flair: Option<String>,
+}
+
+impl From<&PostInfo> for HistoryRecord {
+    fn from(post: &PostInfo) -> Self {
+        let body = if post.selftext.is_empty() {
+            post.url.clone().unwrap_or_default()
+        } else {
+            post.selftext.clone()
+        };
+        HistoryRecord {
+            kind: String::from("post"),
+            name: post.name.clone(),
+            permalink: full_permalink(post.permalink.as_str()),
+            title: Some(post.title.clone()),
+            body,
+            score: post.score,
+            created_utc: post.created_utc,
+            subreddit: post.subreddit.clone(),
+            flair: post.link_flair_text.clone(),
+        }
+    }
+}
+
+impl From<&Comment> for HistoryRecord {
+    fn from(comment: &Comment) -> Self {
+        HistoryRecord {
+            kind: String::from("comment"),
+            name: comment.name.clone(),
+            permalink: full_permalink(comment.permalink.as_str()),
+            title: comment.link_title.clone(),
+            body: comment.body.clone(),
+            score: comment.score,
+            created_utc: comment.created_utc,
+            subreddit: comment.subreddit.clone(),
+            flair: comment.author_flair_text.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ExportSummary {
+    pub posts: usize,
+    pub comments: usize,
+    #[schema(value_type = String)]
+    pub json_lines_path: PathBuf,
+    #[schema(value_type = String)]
+    pub csv_path: PathBuf,
+}
+
+pub async fn export_history(
+    client: &AuthRedditClient,
+    user: &User,
+    export_dir: &str,
+) -> Result<ExportSummary> {
+    let posts = post::retrieve_all_posts_with(client, user, |_| true).await?;
+    let comments = comment::retrieve_all_with(client, user, |_| true).await?;
+
+    let mut records: Vec<HistoryRecord> = posts.iter().map(HistoryRecord::from).collect();
+    records.extend(comments.iter().map(HistoryRecord::from));
+
+    let file_stem = format!("{}-{}", user.name, unix_now());
+    let (json_lines_path, csv_path) = write_records(&records, Path::new(export_dir), &file_stem)?;
+    info!(
+        "Exported {} posts and {} comments to {:?} and {:?}",
+        posts.len(),
+        comments.len(),
+        json_lines_path,
+        csv_path
+    );
+
+    Ok(ExportSummary {
+        posts: posts.len(),
+        comments: comments.len(),
+        json_lines_path,
+        csv_path,
+    })
+}
+
+pub fn write_records(
+    records: &[HistoryRecord],
+    export_dir: &Path,
+    file_stem: &str,
+) -> Result<(PathBuf, PathBuf)> {
+    fs::create_dir_all(export_dir)?;
+
+    let json_lines_path = export_dir.join(format!("{}.jsonl", file_stem));
+    let mut json_lines = BufWriter::new(File::create(&json_lines_path)?);
+    for record in records {
+        serde_json::to_writer(&mut json_lines, record)?;
+        json_lines.write_all(b"\n")?;
+    }
+    json_lines.flush()?;
+
+    let csv_path = export_dir.join(format!("{}.csv", file_stem));
+    let mut csv = csv::Writer::from_path(&csv_path)?;
+    for record in records {
+        csv.serialize(record)?;
+    }
+    csv.flush()?;
+
+    Ok((json_lines_path, csv_path))
+}
+
+fn full_permalink(permalink: &str) -> String {
+    if permalink.is_empty() || permalink.starts_with("http") {
+        return String::from(permalink);
+    }
+    format!("{}{}", REDDIT_URL, permalink)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time before unix epoch")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_are_written_as_json_lines_and_csv() {
+        let records = vec![HistoryRecord {
+            kind: String::from("comment"),
+            name: String::from("t1_abc"),
+            permalink: full_permalink("/r/rust/comments/xyz/title/abc/"),
+            title: Some(String::from("title")),
+            body: String::from("hello, world"),
+            score: 4,
+            created_utc: 1650000000.0,
+            subreddit: String::from("rust"),
+            flair: None,
+        }];
+        let dir = std::env::temp_dir().join(format!("export-test-{}", unix_now()));
+
+        let (json_lines_path, csv_path) = write_records(&records, &dir, "history").unwrap();
+
+        let json_lines = fs::read_to_string(json_lines_path).unwrap();
+        let record: HistoryRecord =
+            serde_json::from_str(json_lines.lines().next().unwrap()).unwrap();
+        assert_eq!(
+            record.permalink,
+            "https://www.reddit.com/r/rust/comments/xyz/title/abc/"
+        );
+        let csv = fs::read_to_string(csv_path).unwrap();
+        assert!(csv.starts_with("kind,name,permalink,title,body,score,created_utc,subreddit,flair"));
+        assert!(csv.contains("\"hello, world\""));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,130 @@
+use crate::data_store::{self, DataStore, PendingLogin};
+use crate::json_file;
+use chrono::Utc;
+use log::{error, info};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+const SESSION_FILE: &str = "session.json";
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Session {
+    #[serde(default)]
+    login_requests: Vec<PendingLogin>,
+    /// Token of each logged in reddit account.
+    #[serde(default)]
+    auth_tokens: BTreeMap<String, String>,
+    default_account: Option<String>,
+    /// Token of the only account before accounts were kept apart, until it's migrated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    auth_token: Option<String>,
+}
+
+/// Keeps the login in `session.json`, so the server and the CLI share it and it survives restarts.
+/// The file is read on every access because the other process may have changed it.
+pub struct FileDataStore {
+    path: PathBuf,
+    lock: RwLock<()>,
+}
+
+impl FileDataStore {
+    pub fn new(data_dir: &str) -> FileDataStore {
+        FileDataStore {
+            path: PathBuf::from(data_dir).join(SESSION_FILE),
+            lock: RwLock::new(()),
+        }
+    }
+
+    fn read(&self) -> Session {
+        let _guard = self
+            .lock
+            .read()
+            .expect("can't obtain the lock to read session");
+        json_file::read(&self.path).unwrap_or_else(|read_error| {
+            error!("Session file is not well-formatted: {}", read_error);
+            Session::default()
+        })
+    }
+
+    fn update(&self, change: impl FnOnce(&mut Session)) {
+        let _guard = self
+            .lock
+            .write()
+            .expect("can't obtain the lock to update session");
+        let mut session: Session = json_file::read(&self.path).unwrap_or_default();
+        change(&mut session);
+        if let Err(write_error) = json_file::write(&self.path, &session) {
+            error!("Can't store session: {}", write_error);
+        }
+    }
+
+    /// Token stored by the versions which knew a single account.
+    pub fn legacy_auth_token(&self) -> Option<String> {
+        self.read().auth_token
+    }
+
+    /// Moves the legacy token to `account`, which becomes the default one unless there is one
+    /// already. A token the account got since is kept.
+    pub fn adopt_legacy_auth_token(&self, account: &str) {
+        self.update(|session| {
+            let Some(auth_token) = session.auth_token.take() else {
+                return;
+            };
+            let key = data_store::account_key(session.auth_tokens.keys(), account);
+            session.auth_tokens.entry(key.clone()).or_insert(auth_token);
+            session.default_account.get_or_insert(key);
+        });
+    }
+}
+
+impl DataStore for FileDataStore {
+    fn store_login_request_id(&self, login_request_id: String) {
+        let now = Utc::now();
+        self.update(|session| {
+            session
+                .login_requests
+                .retain(|login| login.expires_at > now);
+            session
+                .login_requests
+                .push(PendingLogin::new(login_request_id, now))
+        });
+    }
+
+    fn take_login_request_id(&self, login_request_id: &str) -> bool {
+        let mut taken = false;
+        self.update(|session| {
+            taken =
+                data_store::take_pending(&mut session.login_requests, login_request_id, Utc::now())
+        });
+        taken
+    }
+
+    fn store_auth_token(&self, account: &str, auth_token: String) {
+        info!("Storing auth token of {}", account);
+        self.update(|session| {
+            let key = data_store::account_key(session.auth_tokens.keys(), account);
+            session.auth_tokens.insert(key.clone(), auth_token);
+            session.default_account.get_or_insert(key);
+        });
+    }
+
+    fn retrieve_auth_token(&self, account: &str) -> String {
+        self.find_auth_token(account).expect("not logged in")
+    }
+
+    fn find_auth_token(&self, account: &str) -> Option<String> {
+        let session = self.read();
+        let key = data_store::account_key(session.auth_tokens.keys(), account);
+        session.auth_tokens.get(&key).cloned()
+    }
+
+    fn accounts(&self) -> Vec<String> {
+        self.read().auth_tokens.into_keys().collect()
+    }
+
+    fn default_account(&self) -> Option<String> {
+        self.read().default_account
+    }
+}
@@ -1,46 +1,65 @@
 use crate::OAUTH_REDDIT_URL;
-use log::{debug, info};
+use log::{debug, error, info};
 
+use crate::error::Result;
+use crate::job::{ItemStatus, Progress};
 use crate::reddit_client::AuthRedditClient;
 use serde_derive::{Deserialize, Serialize};
 use serde_json::Value;
+use utoipa::ToSchema;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct FlairInfo {
-    sub_reddit: String,
-    text: String,
-    id: String,
+    pub sub_reddit: String,
+    pub text: String,
+    pub id: String,
 }
 
 pub async fn retrieve_flairs_for(
     subreddits: Vec<&str>,
     client: &AuthRedditClient,
+    progress: &impl Progress,
 ) -> Vec<FlairInfo> {
+    let first = progress.add_items(subreddits.iter().map(|name| name.to_string()).collect());
     let mut result = Vec::new();
-    for subreddit in subreddits {
-        let mut flairs = retrieve_flairs(subreddit, client).await;
-        info!("Flairs for {} \n is {:?}", subreddit, flairs);
-        result.append(&mut flairs);
+    for (index, subreddit) in subreddits.into_iter().enumerate() {
+        if progress.is_cancelled() {
+            break;
+        }
+        // private subreddits or ones with flairs turned off fail alone
+        match retrieve_flairs(subreddit, client).await {
+            Ok(mut flairs) => {
+                info!("Flairs for {} \n is {:?}", subreddit, flairs);
+                let detail = format!("{} flairs", flairs.len());
+                progress.finish_item(first + index, ItemStatus::Done, Some(detail));
+                result.append(&mut flairs);
+            }
+            Err(flairs_error) => {
+                error!("Can't read flairs of {}: {}", subreddit, flairs_error);
+                let detail = flairs_error.to_string();
+                progress.finish_item(first + index, ItemStatus::Failed, Some(detail));
+            }
+        }
     }
     result
 }
 
-async fn retrieve_flairs(subreddit: &str, client: &AuthRedditClient) -> Vec<FlairInfo> {
+async fn retrieve_flairs(subreddit: &str, client: &AuthRedditClient) -> Result<Vec<FlairInfo>> {
     let url = format!(
         "{}/r/{}/api/link_flair_v2.json?raw_json=1",
         OAUTH_REDDIT_URL, subreddit
     );
 
-    let body = client.get(url.as_str()).await;
-    let json: Value = serde_json::from_str(body.as_str()).expect("Json format expected");
+    let json: Value = client.get_json(url.as_str()).await?;
     debug!("The json value is {}", json);
-    json.as_array()
+    Ok(json
+        .as_array()
         .unwrap_or(&Vec::new())
         .iter()
         .map(|value| FlairInfo {
             sub_reddit: String::from(subreddit),
-            text: value["text"].to_string(),
-            id: value["id"].to_string(),
+            text: String::from(value["text"].as_str().unwrap_or_default()),
+            id: String::from(value["id"].as_str().unwrap_or_default()),
         })
-        .collect()
+        .collect())
 }
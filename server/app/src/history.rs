@@ -0,0 +1,244 @@
+use crate::error::Result;
+use crate::json_file;
+use crate::post::{self, FinalPost, PostInfo};
+use crate::reddit_client::AuthRedditClient;
+use chrono::{DateTime, Duration, Utc};
+use log::{error, info};
+use serde_derive::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::RwLock;
+use utoipa::ToSchema;
+
+pub const HISTORY_FILE: &str = "history.json";
+/// Age of a submission, in minutes, at which its performance is snapshotted.
+const SNAPSHOT_MINUTES: [i64; 5] = [15, 60, 6 * 60, 24 * 60, 7 * 24 * 60];
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct Snapshot {
+    pub taken_at: DateTime<Utc>,
+    pub age_minutes: i64,
+    pub score: i64,
+    pub upvote_ratio: f64,
+    pub num_comments: u64,
+    pub removed_by_category: Option<String>,
+}
+
+/// One of our own submissions.
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct Submission {
+    pub id: String,
+    pub subreddit: String,
+    pub title: String,
+    pub url: String,
+    pub posted_at: DateTime<Utc>,
+    #[serde(default)]
+    pub snapshots: Vec<Snapshot>,
+    #[serde(default)]
+    pub next_snapshot: usize,
+}
+
+impl Submission {
+    pub fn new(id: String, post: &FinalPost, posted_at: DateTime<Utc>) -> Submission {
+        Submission {
+            id,
+            subreddit: post.subreddit.clone(),
+            title: post.title.clone(),
+            url: post.url.clone(),
+            posted_at,
+            snapshots: Vec::new(),
+            next_snapshot: 0,
+        }
+    }
+
+    pub fn fullname(&self) -> String {
+        format!("t3_{}", self.id)
+    }
+
+    fn next_snapshot_at(&self) -> Option<DateTime<Utc>> {
+        SNAPSHOT_MINUTES
+            .get(self.next_snapshot)
+            .map(|minutes| self.posted_at + Duration::minutes(*minutes))
+    }
+}
+
+/// Time series of a submission, one entry per snapshot.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Chart {
+    pub id: String,
+    pub subreddit: String,
+    pub title: String,
+    pub posted_at: DateTime<Utc>,
+    pub age_minutes: Vec<i64>,
+    pub score: Vec<i64>,
+    pub upvote_ratio: Vec<f64>,
+    pub num_comments: Vec<u64>,
+    pub removed: Vec<bool>,
+}
+
+impl From<&Submission> for Chart {
+    fn from(submission: &Submission) -> Self {
+        let snapshots = &submission.snapshots;
+        Chart {
+            id: submission.id.clone(),
+            subreddit: submission.subreddit.clone(),
+            title: submission.title.clone(),
+            posted_at: submission.posted_at,
+            age_minutes: snapshots.iter().map(|s| s.age_minutes).collect(),
+            score: snapshots.iter().map(|s| s.score).collect(),
+            upvote_ratio: snapshots.iter().map(|s| s.upvote_ratio).collect(),
+            num_comments: snapshots.iter().map(|s| s.num_comments).collect(),
+            removed: snapshots
+                .iter()
+                .map(|s| s.removed_by_category.is_some())
+                .collect(),
+        }
+    }
+}
+
+/// Submissions made through this application, persisted to `history.json`.
+/// The file is read on every access because the CLI and the server both write it.
+pub struct History {
+    path: PathBuf,
+    lock: RwLock<()>,
+}
+
+impl History {
+    pub fn load(data_dir: &str) -> Result<History> {
+        let path = PathBuf::from(data_dir).join(HISTORY_FILE);
+        json_file::read::<Vec<Submission>>(&path)?;
+        Ok(History {
+            path,
+            lock: RwLock::new(()),
+        })
+    }
+
+    pub fn list(&self) -> Vec<Submission> {
+        let _guard = self
+            .lock
+            .read()
+            .expect("can't obtain the lock to read history");
+        json_file::read(&self.path).unwrap_or_else(|read_error| {
+            error!("History file is not well-formatted: {}", read_error);
+            Vec::new()
+        })
+    }
+
+    pub fn chart(&self, id: &str) -> Option<Chart> {
+        self.list()
+            .iter()
+            .find(|submission| submission.id == id)
+            .map(Chart::from)
+    }
+
+    pub fn record(&self, submission: Submission) -> Result<()> {
+        self.update(|submissions| submissions.push(submission))
+    }
+
+    pub fn due_for_snapshot(&self, now: DateTime<Utc>) -> Vec<String> {
+        self.list()
+            .iter()
+            .filter(|submission| submission.next_snapshot_at().is_some_and(|at| at <= now))
+            .map(Submission::fullname)
+            .collect()
+    }
+
+    /// Appends a snapshot for every given post and skips the intervals which already passed.
+    pub fn add_snapshots(&self, infos: &[PostInfo], now: DateTime<Utc>) -> Result<()> {
+        self.update(|submissions| {
+            for info in infos {
+                let submission = match submissions.iter_mut().find(|s| s.id == info.id) {
+                    Some(submission) => submission,
+                    None => continue,
+                };
+                let age_minutes = (now - submission.posted_at).num_minutes();
+                submission.snapshots.push(Snapshot {
+                    taken_at: now,
+                    age_minutes,
+                    score: info.score,
+                    upvote_ratio: info.upvote_ratio,
+                    num_comments: info.num_comments,
+                    removed_by_category: info.removed_by_category.clone(),
+                });
+                submission.next_snapshot = SNAPSHOT_MINUTES
+                    .iter()
+                    .filter(|minutes| **minutes <= age_minutes)
+                    .count();
+            }
+        })
+    }
+
+    /// Changes the latest content of the file, a malformed file is left as it is.
+    fn update(&self, change: impl FnOnce(&mut Vec<Submission>)) -> Result<()> {
+        let _guard = self
+            .lock
+            .write()
+            .expect("can't obtain the lock to update history");
+        let mut submissions = json_file::read(&self.path)?;
+        change(&mut submissions);
+        json_file::write(&self.path, &submissions)
+    }
+
+    /// Snapshots performance of the submissions which reached their next interval.
+    pub async fn snapshot_due(&self, client: &AuthRedditClient) {
+        let due = self.due_for_snapshot(Utc::now());
+        if due.is_empty() {
+            return;
+        }
+        info!("Taking performance snapshot of {} posts", due.len());
+        let result = match post::info_by_names(client, &due).await {
+            Ok(infos) => self.add_snapshots(&infos, Utc::now()),
+            Err(error) => Err(error),
+        };
+        if let Err(error) = result {
+            error!("Can't take performance snapshot: {}", error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{post_info, submission};
+    use chrono::TimeZone;
+
+    #[test]
+    fn snapshots_follow_increasing_intervals() {
+        let dir = std::env::temp_dir().join(format!("history-test-{}", uuid::Uuid::new_v4()));
+        let history = History::load(dir.to_str().unwrap()).unwrap();
+        let posted_at = Utc.with_ymd_and_hms(2023, 6, 5, 8, 0, 0).unwrap();
+        history
+            .record(submission("abc", "rust", "https://example.com", posted_at))
+            .unwrap();
+        let info = PostInfo {
+            num_comments: 3,
+            upvote_ratio: 0.8,
+            ..post_info("abc", "rust", posted_at, 12)
+        };
+
+        assert!(history
+            .due_for_snapshot(posted_at + Duration::minutes(14))
+            .is_empty());
+        assert_eq!(
+            history.due_for_snapshot(posted_at + Duration::minutes(15)),
+            vec!["t3_abc"]
+        );
+        // the server was down for two hours, the 15m and 1h snapshots collapse into one
+        history
+            .add_snapshots(&[info], posted_at + Duration::minutes(120))
+            .unwrap();
+        assert!(history
+            .due_for_snapshot(posted_at + Duration::minutes(359))
+            .is_empty());
+        assert_eq!(
+            history.due_for_snapshot(posted_at + Duration::minutes(360)),
+            vec!["t3_abc"]
+        );
+        let chart = History::load(dir.to_str().unwrap())
+            .unwrap()
+            .chart("abc")
+            .unwrap();
+        assert_eq!(chart.age_minutes, vec![120]);
+        assert_eq!(chart.score, vec![12]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,55 +1,122 @@
-use crate::data_store::DataStore;
+use crate::data_store::{self, DataStore, PendingLogin};
+use chrono::Utc;
 use log::info;
+use std::collections::BTreeMap;
 use std::sync::RwLock;
 
 pub struct InMemoryDataStore {
-    login_request_id: RwLock<Option<String>>,
-    auth_token: RwLock<Option<String>>,
+    login_requests: RwLock<Vec<PendingLogin>>,
+    auth_tokens: RwLock<BTreeMap<String, String>>,
+    default_account: RwLock<Option<String>>,
 }
 
 impl InMemoryDataStore {
     pub fn new() -> InMemoryDataStore {
         InMemoryDataStore {
-            login_request_id: RwLock::new(None),
-            auth_token: RwLock::new(None),
+            login_requests: RwLock::new(Vec::new()),
+            auth_tokens: RwLock::new(BTreeMap::new()),
+            default_account: RwLock::new(None),
         }
     }
 }
 
+impl Default for InMemoryDataStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl DataStore for InMemoryDataStore {
     fn store_login_request_id(&self, login_request_id: String) {
-        let _ = self
-            .login_request_id
+        let now = Utc::now();
+        let mut login_requests = self
+            .login_requests
             .write()
-            .expect("can't obtain the lock to store login request id")
-            .insert(login_request_id);
+            .expect("can't obtain the lock to store login request id");
+        login_requests.retain(|login| login.expires_at > now);
+        login_requests.push(PendingLogin::new(login_request_id, now));
     }
 
-    fn retrieve_login_request_id(&self) -> String {
-        self.login_request_id
-            .read()
-            .expect("can't obtain the lock to retrieve login request id")
-            .as_ref()
-            .unwrap()
-            .clone()
+    fn take_login_request_id(&self, login_request_id: &str) -> bool {
+        let mut login_requests = self
+            .login_requests
+            .write()
+            .expect("can't obtain the lock to take login request id");
+        data_store::take_pending(&mut login_requests, login_request_id, Utc::now())
     }
 
-    fn store_auth_token(&self, auth_token: String) {
-        info!("Storing auth token {:?}", auth_token);
+    fn store_auth_token(&self, account: &str, auth_token: String) {
+        info!("Storing auth token of {}", account);
 
-        let _ = self
-            .auth_token
+        let mut auth_tokens = self
+            .auth_tokens
             .write()
-            .expect("can't obtain the lock to store token")
-            .insert(auth_token);
+            .expect("can't obtain the lock to store token");
+        let key = data_store::account_key(auth_tokens.keys(), account);
+        auth_tokens.insert(key.clone(), auth_token);
+        self.default_account
+            .write()
+            .expect("can't obtain the lock to store default account")
+            .get_or_insert(key);
+    }
+
+    fn retrieve_auth_token(&self, account: &str) -> String {
+        self.find_auth_token(account).unwrap()
+    }
+
+    fn find_auth_token(&self, account: &str) -> Option<String> {
+        let auth_tokens = self
+            .auth_tokens
+            .read()
+            .expect("can't obtain the lock to retrieve token");
+        let key = data_store::account_key(auth_tokens.keys(), account);
+        auth_tokens.get(&key).cloned()
     }
 
-    fn retrieve_auth_token(&self) -> String {
-        self.auth_token
+    fn accounts(&self) -> Vec<String> {
+        self.auth_tokens
             .read()
             .expect("can't obtain the lock to retrieve token")
-            .as_ref()
-            .unwrap()
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    fn default_account(&self) -> Option<String> {
+        self.default_account
+            .read()
+            .expect("can't obtain the lock to retrieve default account")
             .clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn login_requests_are_taken_once() {
+        let store = InMemoryDataStore::new();
+        store.store_login_request_id(String::from("first"));
+        store.store_login_request_id(String::from("second"));
+
+        assert!(store.take_login_request_id("second"));
+        assert!(store.take_login_request_id("first"));
+        assert!(!store.take_login_request_id("first"));
+        assert!(!store.take_login_request_id("unknown"));
+    }
+
+    #[test]
+    fn tokens_are_kept_per_account() {
+        let store = InMemoryDataStore::new();
+        store.store_auth_token("Brand", String::from("first"));
+        store.store_auth_token("other", String::from("second"));
+        store.store_auth_token("brand", String::from("third"));
+
+        assert_eq!(store.accounts(), vec!["Brand", "other"]);
+        assert_eq!(store.default_account().as_deref(), Some("Brand"));
+        assert_eq!(store.find_auth_token("BRAND").as_deref(), Some("third"));
+        assert_eq!(store.retrieve_auth_token("other"), "second");
+        assert!(store.find_auth_token("unknown").is_none());
+    }
+}
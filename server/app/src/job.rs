@@ -0,0 +1,291 @@
+use crate::error::{Error, Result};
+use chrono::{DateTime, Duration, Utc};
+use futures::FutureExt;
+use log::{error, info};
+use serde_derive::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Finished jobs are forgotten after this many hours.
+const KEEP_FINISHED_HOURS: i64 = 24;
+
+#[derive(Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemStatus {
+    Pending,
+    Done,
+    Skipped,
+    Failed,
+    Cancelled,
+}
+
+/// One post, comment or subreddit a job works on.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct JobItem {
+    pub name: String,
+    pub status: ItemStatus,
+    pub detail: Option<String>,
+}
+
+/// Reports the progress of a long operation item by item and tells it when to stop early.
+pub trait Progress {
+    /// Adds items which are going to be worked on, returns the index of the first one.
+    fn add_items(&self, names: Vec<String>) -> usize;
+
+    fn finish_item(&self, index: usize, status: ItemStatus, detail: Option<String>);
+
+    /// Checked between items, the operation returns what it has done so far when set.
+    fn is_cancelled(&self) -> bool;
+}
+
+/// Progress nobody watches, for the command line and the periodic runners.
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn add_items(&self, _: Vec<String>) -> usize {
+        0
+    }
+
+    fn finish_item(&self, _: usize, _: ItemStatus, _: Option<String>) {}
+
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+/// A long operation running in the background, `result` is set once it is finished.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct Job {
+    pub id: String,
+    pub kind: String,
+    pub account: String,
+    pub status: JobStatus,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub total: usize,
+    pub completed: usize,
+    pub items: Vec<JobItem>,
+    pub result: Option<Value>,
+    pub error: Option<String>,
+    #[serde(skip)]
+    cancel: Arc<AtomicBool>,
+}
+
+impl Job {
+    pub fn is_finished(&self) -> bool {
+        self.status != JobStatus::Running
+    }
+}
+
+/// Jobs of this server run, they are not persisted.
+#[derive(Default)]
+pub struct Jobs {
+    jobs: RwLock<HashMap<String, Job>>,
+}
+
+impl Jobs {
+    pub fn new() -> Jobs {
+        Jobs::default()
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.jobs
+            .read()
+            .expect("can't obtain the lock to read jobs")
+            .get(id)
+            .cloned()
+    }
+
+    /// Asks a running job to stop after its current item, `None` when there is no such job.
+    pub fn cancel(&self, id: &str) -> Option<Job> {
+        let job = self.get(id)?;
+        if !job.is_finished() {
+            info!("Cancelling job {}", id);
+            job.cancel.store(true, Ordering::Relaxed);
+        }
+        Some(job)
+    }
+
+    /// Runs `work` on the current actix runtime and returns the id of its job right away.
+    pub fn spawn<T, F>(
+        self: Arc<Self>,
+        kind: &str,
+        account: &str,
+        work: impl FnOnce(JobHandle) -> F,
+    ) -> String
+    where
+        T: serde::Serialize,
+        F: Future<Output = Result<T>> + 'static,
+    {
+        let now = Utc::now();
+        let job = Job {
+            id: Uuid::new_v4().to_string(),
+            kind: kind.to_string(),
+            account: account.to_string(),
+            status: JobStatus::Running,
+            started_at: now,
+            finished_at: None,
+            total: 0,
+            completed: 0,
+            items: Vec::new(),
+            result: None,
+            error: None,
+            cancel: Arc::new(AtomicBool::new(false)),
+        };
+        let id = job.id.clone();
+        let cancel = job.cancel.clone();
+        info!("Starting {} job {}", kind, id);
+        {
+            let mut jobs = self
+                .jobs
+                .write()
+                .expect("can't obtain the lock to update jobs");
+            jobs.retain(|_, job| {
+                job.finished_at
+                    .is_none_or(|at| at + Duration::hours(KEEP_FINISHED_HOURS) > now)
+            });
+            jobs.insert(id.clone(), job);
+        }
+        let work = work(JobHandle {
+            jobs: self.clone(),
+            id: id.clone(),
+            cancel,
+        });
+        let job_id = id.clone();
+        actix_web::rt::spawn(async move {
+            // a few reddit calls still panic on errors, the job must not stay running then
+            let outcome = match AssertUnwindSafe(work).catch_unwind().await {
+                Ok(outcome) => outcome.and_then(|result| Ok(serde_json::to_value(result)?)),
+                Err(_) => Err(Error::Reddit(String::from("the job panicked, see the log"))),
+            };
+            self.update(&job_id, |job| {
+                job.finished_at = Some(Utc::now());
+                let cancelled = job.cancel.load(Ordering::Relaxed);
+                match outcome {
+                    Ok(result) => {
+                        job.status = if cancelled {
+                            JobStatus::Cancelled
+                        } else {
+                            JobStatus::Done
+                        };
+                        job.result = Some(result);
+                    }
+                    Err(job_error) => {
+                        error!("Job {} failed: {}", job.id, job_error);
+                        job.status = JobStatus::Failed;
+                        job.error = Some(job_error.to_string());
+                    }
+                }
+                let stopped = job.status == JobStatus::Cancelled;
+                for item in job.items.iter_mut().filter(|_| stopped) {
+                    if item.status == ItemStatus::Pending {
+                        item.status = ItemStatus::Cancelled;
+                    }
+                }
+            });
+        });
+        id
+    }
+
+    fn update<T>(&self, id: &str, change: impl FnOnce(&mut Job) -> T) -> Option<T> {
+        self.jobs
+            .write()
+            .expect("can't obtain the lock to update jobs")
+            .get_mut(id)
+            .map(change)
+    }
+}
+
+/// Progress of one job, handed to its work.
+pub struct JobHandle {
+    jobs: Arc<Jobs>,
+    id: String,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Progress for JobHandle {
+    fn add_items(&self, names: Vec<String>) -> usize {
+        self.jobs
+            .update(&self.id, |job| {
+                let first = job.items.len();
+                job.items.extend(names.into_iter().map(|name| JobItem {
+                    name,
+                    status: ItemStatus::Pending,
+                    detail: None,
+                }));
+                job.total = job.items.len();
+                first
+            })
+            .unwrap_or(0)
+    }
+
+    fn finish_item(&self, index: usize, status: ItemStatus, detail: Option<String>) {
+        self.jobs.update(&self.id, |job| {
+            if let Some(item) = job.items.get_mut(index) {
+                item.status = status;
+                item.detail = detail;
+                job.completed += 1;
+            }
+        });
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn cancelled_jobs_stop_between_items() {
+        let jobs = Arc::new(Jobs::new());
+        let (started, first_done) = futures::channel::oneshot::channel();
+        let id = jobs.clone().spawn("count", "me", |job| async move {
+            let first = job.add_items(vec![String::from("one"), String::from("two")]);
+            job.finish_item(first, ItemStatus::Done, None);
+            started.send(()).expect("the test waits for the first item");
+            while !job.is_cancelled() {
+                actix_web::rt::time::sleep(std::time::Duration::from_millis(1)).await;
+            }
+            Ok(1)
+        });
+
+        first_done.await.unwrap();
+        let running = jobs.get(&id).unwrap();
+        assert_eq!(
+            (running.status, running.total, running.completed),
+            (JobStatus::Running, 2, 1)
+        );
+        jobs.cancel(&id);
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        let cancelled = loop {
+            let job = jobs.get(&id).unwrap();
+            if job.status != JobStatus::Running {
+                break job;
+            }
+            assert!(std::time::Instant::now() < deadline, "the job didn't stop");
+            actix_web::rt::time::sleep(std::time::Duration::from_millis(1)).await;
+        };
+        assert_eq!(cancelled.status, JobStatus::Cancelled);
+        assert_eq!(cancelled.items[1].status, ItemStatus::Cancelled);
+        assert_eq!(cancelled.result, Some(Value::from(1)));
+        assert!(jobs.cancel("unknown").is_none());
+    }
+}
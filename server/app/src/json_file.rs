@@ -0,0 +1,24 @@
+use crate::error::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+/// Reads the JSON file at `path`, a missing file gives the default value.
+pub fn read<T: DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(serde_json::from_str(&content)?),
+        Err(_) => Ok(T::default()),
+    }
+}
+
+/// Replaces the file at `path` through a temporary file so a crash never leaves half a file.
+pub fn write<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, serde_json::to_string_pretty(value)?)?;
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
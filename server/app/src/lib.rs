@@ -1,13 +1,35 @@
+pub mod account;
+pub mod api_error;
+pub mod api_key;
+pub mod auto_schedule;
+pub mod best_time;
 pub mod comment;
+pub mod cooldown;
 pub mod data_store;
+pub mod duplicate;
+pub mod eligibility;
 pub mod environment;
+pub mod error;
+pub mod export;
+pub mod file_data_store;
 pub mod flairs;
+pub mod history;
 pub mod in_memory_data_store;
+pub mod job;
+pub mod json_file;
+pub mod listing;
 pub mod login;
 pub mod media;
+pub mod performance;
 pub mod post;
 pub mod reddit_client;
+pub mod removal;
+pub mod retention;
+pub mod schedule;
+pub mod stats;
 pub mod subreddit;
+#[cfg(test)]
+mod test_support;
 pub mod user;
 
 use serde_derive::Deserialize;
@@ -18,10 +40,10 @@ const OAUTH_REDDIT_URL: &str = "https://oauth.reddit.com";
 #[derive(Debug, Deserialize)]
 pub struct AuthToken {
     pub access_token: String,
-    token_type: String,
-    expires_in: u32,
-    scope: String,
-    refresh_token: Option<String>,
+    pub token_type: String,
+    pub expires_in: u32,
+    pub scope: String,
+    pub refresh_token: Option<String>,
 }
 
 #[cfg(test)]
@@ -0,0 +1,272 @@
+use crate::comment::Comment;
+use crate::error::{Error, Result};
+use crate::post::PostInfo;
+use crate::reddit_client::AuthRedditClient;
+use crate::user::User;
+use crate::OAUTH_REDDIT_URL;
+use futures::{stream, Stream, TryStreamExt};
+use log::info;
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde_derive::Deserialize;
+
+//https://www.reddit.com/dev/api#listings
+
+const PAGE_LIMIT: u32 = 100;
+
+#[derive(Deserialize, Debug)]
+pub struct Thing<T> {
+    pub kind: String,
+    pub data: T,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Listing<C> {
+    pub after: Option<String>,
+    pub before: Option<String>,
+    pub dist: Option<u64>,
+    pub children: Vec<C>,
+}
+
+/// Entry of a listing which mixes comments and posts, like overview or saved.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "kind", content = "data")]
+pub enum Item {
+    #[serde(rename = "t1")]
+    Comment(Comment),
+    #[serde(rename = "t3")]
+    Post(PostInfo),
+}
+
+#[derive(Debug, Clone)]
+pub enum Cursor {
+    After(Option<String>),
+    Before(String),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum UserListing {
+    Submitted,
+    Comments,
+    Overview,
+    Saved,
+    Hidden,
+}
+
+impl UserListing {
+    pub fn url(&self, user: &User) -> String {
+        let path = match self {
+            UserListing::Submitted => "submitted",
+            UserListing::Comments => "comments",
+            UserListing::Overview => "overview",
+            UserListing::Saved => "saved",
+            UserListing::Hidden => "hidden",
+        };
+        format!("{}{}{}", OAUTH_REDDIT_URL, user.url(), path)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum TimeFilter {
+    Hour,
+    Day,
+    Week,
+    Month,
+    Year,
+    All,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SubredditSort {
+    Hot,
+    New,
+    Rising,
+    Top(TimeFilter),
+}
+
+impl SubredditSort {
+    pub fn url(&self, subreddit: &str) -> String {
+        let (sort, time_filter) = match self {
+            SubredditSort::Hot => ("hot", None),
+            SubredditSort::New => ("new", None),
+            SubredditSort::Rising => ("rising", None),
+            SubredditSort::Top(time_filter) => ("top", Some(time_filter)),
+        };
+        let url = format!("{}/r/{}/{}", OAUTH_REDDIT_URL, subreddit, sort);
+        match time_filter {
+            Some(time_filter) => format!("{}?t={}", url, time_filter.as_str()),
+            None => url,
+        }
+    }
+}
+
+impl TimeFilter {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimeFilter::Hour => "hour",
+            TimeFilter::Day => "day",
+            TimeFilter::Week => "week",
+            TimeFilter::Month => "month",
+            TimeFilter::Year => "year",
+            TimeFilter::All => "all",
+        }
+    }
+}
+
+/// Walks every page of the listing at `url` by following its `after` cursor.
+pub fn paginate<'a, T>(
+    client: &'a AuthRedditClient,
+    url: String,
+) -> impl Stream<Item = Result<T>> + 'a
+where
+    T: DeserializeOwned + 'a,
+{
+    paginate_from(client, url, Cursor::After(None))
+}
+
+pub fn paginate_from<'a, T>(
+    client: &'a AuthRedditClient,
+    url: String,
+    cursor: Cursor,
+) -> impl Stream<Item = Result<T>> + 'a
+where
+    T: DeserializeOwned + 'a,
+{
+    paginate_children::<Thing<T>>(client, url, cursor).map_ok(|thing| thing.data)
+}
+
+pub fn user_items<'a>(
+    client: &'a AuthRedditClient,
+    user: &User,
+    listing: UserListing,
+) -> impl Stream<Item = Result<Item>> + 'a {
+    paginate_children(client, listing.url(user), Cursor::After(None))
+}
+
+pub fn subreddit_posts<'a>(
+    client: &'a AuthRedditClient,
+    subreddit: &str,
+    sort: SubredditSort,
+) -> impl Stream<Item = Result<PostInfo>> + 'a {
+    paginate(client, sort.url(subreddit))
+}
+
+struct PageState {
+    cursor: Option<Cursor>,
+    count: usize,
+}
+
+fn paginate_children<'a, C>(
+    client: &'a AuthRedditClient,
+    url: String,
+    cursor: Cursor,
+) -> impl Stream<Item = Result<C>> + 'a
+where
+    C: DeserializeOwned + 'a,
+{
+    let state = PageState {
+        cursor: Some(cursor),
+        count: 0,
+    };
+    stream::try_unfold(state, move |state| {
+        let url = url.clone();
+        async move {
+            let cursor = match state.cursor {
+                Some(cursor) => cursor,
+                None => return Ok(None),
+            };
+            let page_url = page_url(url.as_str(), &cursor, state.count);
+            let listing: Thing<Listing<C>> = client.get_json(page_url.as_str()).await?;
+            let listing = listing.data;
+            info!("Retrieved {} children from {}", listing.children.len(), url);
+
+            let next = match cursor {
+                Cursor::After(_) => listing.after.map(|after| Cursor::After(Some(after))),
+                Cursor::Before(_) => listing.before.map(Cursor::Before),
+            };
+            let next = if listing.children.is_empty() {
+                None
+            } else {
+                next
+            };
+            let state = PageState {
+                cursor: next,
+                count: state.count + listing.children.len(),
+            };
+            Ok::<_, Error>(Some((listing.children, state)))
+        }
+    })
+    .map_ok(|children| stream::iter(children.into_iter().map(Ok)))
+    .try_flatten()
+}
+
+fn page_url(url: &str, cursor: &Cursor, count: usize) -> String {
+    let mut page_url = Url::parse(url).expect("listing url should be valid");
+    {
+        let mut query = page_url.query_pairs_mut();
+        query.append_pair("limit", PAGE_LIMIT.to_string().as_str());
+        if count > 0 {
+            query.append_pair("count", count.to_string().as_str());
+        }
+        match cursor {
+            Cursor::After(Some(after)) => {
+                query.append_pair("after", after);
+            }
+            Cursor::After(None) => {}
+            Cursor::Before(before) => {
+                query.append_pair("before", before);
+            }
+        }
+    }
+    page_url.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_url_keeps_existing_query() {
+        let url = page_url(
+            "https://oauth.reddit.com/r/rust/top?t=week",
+            &Cursor::After(Some(String::from("t3_abc"))),
+            100,
+        );
+
+        assert_eq!(
+            url,
+            "https://oauth.reddit.com/r/rust/top?t=week&limit=100&count=100&after=t3_abc"
+        );
+    }
+
+    #[test]
+    fn mixed_listing_is_deserialized() {
+        let body = r#"{"kind": "Listing", "data": {"after": null, "before": null, "dist": 2,
+            "children": [
+                {"kind": "t1", "data": {"id": "c1", "name": "t1_c1", "body": "hi", "ups": 3, "subreddit": "rust", "created_utc": 1650000000.0}},
+                {"kind": "t3", "data": {"id": "p1", "name": "t3_p1", "ups": 7, "subreddit": "rust", "created_utc": 1650000000.0}}
+            ]}}"#;
+
+        let listing: Thing<Listing<Item>> = serde_json::from_str(body).unwrap();
+
+        assert_eq!(listing.data.children.len(), 2);
+        assert!(matches!(listing.data.children[0], Item::Comment(_)));
+        assert!(matches!(&listing.data.children[1], Item::Post(post) if post.upvotes == 7));
+    }
+
+    #[test]
+    fn downvoted_items_are_deserialized() {
+        let body = r#"{"kind": "Listing", "data": {"after": null, "before": null, "dist": 2,
+            "children": [
+                {"kind": "t1", "data": {"id": "c1", "name": "t1_c1", "body": "hi", "ups": -4, "subreddit": "rust", "created_utc": 1650000000.0}},
+                {"kind": "t3", "data": {"id": "p1", "name": "t3_p1", "ups": -1, "subreddit": "rust", "created_utc": 1650000000.0}}
+            ]}}"#;
+
+        let listing: Thing<Listing<Item>> = serde_json::from_str(body).unwrap();
+
+        assert!(
+            matches!(&listing.data.children[0], Item::Comment(comment) if comment.upvotes == -4)
+        );
+        assert!(matches!(&listing.data.children[1], Item::Post(post) if post.upvotes == -1));
+    }
+}
@@ -1,18 +1,49 @@
+use crate::data_store::DataStore;
 use crate::environment::Environment;
+use crate::error::{Error, Result};
 use crate::reddit_client::AuthRedditClient;
+use crate::user;
 use crate::AuthToken;
-use log::info;
+use log::{debug, error, info};
+use reqwest::Url;
 use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
 use string_template::Template;
+use utoipa::ToSchema;
 use uuid::Uuid;
-use webbrowser;
 
 //https://github.com/reddit-archive/reddit/wiki/OAuth2
 
 const REQUEST_LOGIN_URL: &str = "https://www.reddit.com/api/v1/authorize?client_id={{APP_ID}}&response_type=code&state={{LOGIN_REQUEST_ID}}&redirect_uri={{APP_REDIRECT_URL}}&duration=temporary&scope={{APP_SCOPE}}";
 
-pub async fn request_login(settings: Environment) -> String {
+/// Authorization URL to open in a browser, `id` comes back as `state` in the callback.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct LoginRequest {
+    pub id: String,
+    pub url: String,
+}
+
+/// Opens the authorization URL in the browser, or only logs it in headless mode or when there
+/// is no browser.
+pub async fn request_login(settings: Environment) -> LoginRequest {
+    let headless = settings.headless;
+    let login_request = login_url(settings);
+    if headless {
+        info!("Open {} to log in", login_request.url);
+    } else {
+        info!("Opening browser...");
+        if let Err(open_error) = webbrowser::open(&login_request.url) {
+            error!(
+                "Can't open the browser ({}), open {} to log in",
+                open_error, login_request.url
+            );
+        }
+    }
+    login_request
+}
+
+pub fn login_url(settings: Environment) -> LoginRequest {
     let template_login = Template::new(REQUEST_LOGIN_URL);
 
     let login_request_id = Uuid::new_v4();
@@ -28,17 +59,37 @@ pub async fn request_login(settings: Environment) -> String {
     args.insert("APP_SCOPE", settings.application_scope.as_str());
 
     let url = template_login.render(&args);
-    info!("Opening browser...");
-    webbrowser::open(&url).expect("failed to open URL");
+    LoginRequest {
+        id: login_request_id,
+        url,
+    }
+}
 
-    login_request_id
+/// Code and state from the pasted callback URL, or the pasted code itself.
+pub fn parse_callback(input: &str) -> Result<(String, Option<String>)> {
+    let input = input.trim();
+    let url = match Url::parse(input) {
+        Ok(url) => url,
+        Err(_) if !input.is_empty() && !input.contains(['/', '?', '&', ' ']) => {
+            return Ok((String::from(input), None))
+        }
+        Err(parse_error) => return Err(Error::Reddit(parse_error.to_string())),
+    };
+    let query = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    if let Some(denied) = query("error") {
+        return Err(Error::Reddit(format!("login failed: {}", denied)));
+    }
+    let code =
+        query("code").ok_or_else(|| Error::Reddit(String::from("no code in the callback URL")))?;
+    Ok((code, query("state")))
 }
 
-pub async fn auth_token_for(code: &str, settings: Environment) -> AuthToken {
+pub async fn auth_token_for(code: &str, settings: Environment) -> Result<AuthToken> {
     info!("Trying to receive token for code {}", code);
-    let client = reqwest::Client::builder()
-        .build()
-        .expect("error during client build");
 
     // This will POST a body of `{"lang":"rust","body":"json"}`
     let data = NetworkData {
@@ -47,7 +98,64 @@ pub async fn auth_token_for(code: &str, settings: Environment) -> AuthToken {
         redirect_uri: settings.application_redirection_link.clone(),
     };
 
-    let result = AuthRedditClient::add_headers(
+    request_token(&settings, &data).await
+}
+
+/// Logs in a script app with the account from `REDDIT_USERNAME` and `REDDIT_PASSWORD`.
+pub async fn password_token(settings: &Environment) -> Result<AuthToken> {
+    let (username, password) = match (&settings.username, &settings.password) {
+        (Some(username), Some(password)) => (username.clone(), password.clone()),
+        _ => {
+            return Err(Error::Config(String::from(
+                "REDDIT_USERNAME and REDDIT_PASSWORD are needed for the password flow",
+            )))
+        }
+    };
+    info!("Trying to receive token for {}", username);
+    let data = PasswordData {
+        grant_type: String::from("password"),
+        username,
+        password,
+        scope: settings.application_scope.replace(',', " "),
+    };
+    request_token(settings, &data).await
+}
+
+/// Stores the token under the name of the account it belongs to and returns that name.
+pub async fn store_login(data_store: &impl DataStore, token: AuthToken) -> Result<String> {
+    let client = AuthRedditClient::new(token.access_token.clone());
+    let account = user::me(&client).await?.name;
+    info!("Logged in as {}", account);
+    data_store.store_auth_token(&account, token.access_token);
+    Ok(account)
+}
+
+/// Logs in again before the token of the password flow expires, forever.
+pub async fn keep_logged_in(data_store: &impl DataStore) {
+    loop {
+        let settings = Environment::read_env();
+        let login = match password_token(&settings).await {
+            Ok(token) => {
+                let expires_in = token.expires_in as u64;
+                store_login(data_store, token).await.map(|_| expires_in)
+            }
+            Err(login_error) => Err(login_error),
+        };
+        let wait_seconds = match login {
+            Ok(expires_in) => expires_in.saturating_sub(5 * 60).max(60),
+            Err(login_error) => {
+                error!("Can't log in with password: {}", login_error);
+                60
+            }
+        };
+        actix_web::rt::time::sleep(std::time::Duration::from_secs(wait_seconds)).await;
+    }
+}
+
+/// Installed apps have no secret, reddit expects an empty password then.
+async fn request_token<T: serde::Serialize>(settings: &Environment, data: &T) -> Result<AuthToken> {
+    let client = reqwest::Client::builder().build()?;
+    let body = AuthRedditClient::add_headers(
         client
             .post("https://www.reddit.com/api/v1/access_token")
             .basic_auth(
@@ -55,18 +163,18 @@ pub async fn auth_token_for(code: &str, settings: Environment) -> AuthToken {
                 Some(settings.application_secret.as_str()),
             ),
     )
-    .body(serde_urlencoded::to_string(&data).expect("serialize issue during obtain auth token"))
+    .body(serde_urlencoded::to_string(data).expect("serialize issue during obtain auth token"))
     .send()
-    .await;
-
-    let body = result
-        .expect("Result is empty")
-        .text()
-        .await
-        .expect("Body is empty");
-    info!("Result body is {:?}", body);
+    .await?
+    .text()
+    .await?;
+    debug!("Result body is {:?}", body);
 
-    serde_json::from_str(&body).expect("Bad body spec")
+    let value: Value = serde_json::from_str(&body)?;
+    if let Some(login_error) = value.get("error") {
+        return Err(Error::Reddit(format!("login failed: {}", login_error)));
+    }
+    Ok(serde_json::from_value(value)?)
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -75,3 +183,27 @@ pub struct NetworkData {
     code: String,
     redirect_uri: String,
 }
+
+#[derive(Serialize, Debug)]
+struct PasswordData {
+    grant_type: String,
+    username: String,
+    password: String,
+    scope: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pasted_callback_gives_the_code() {
+        let (code, state) =
+            parse_callback(" http://127.0.0.1:9090/reddit/login-callback?state=abc&code=xyz#_\n")
+                .unwrap();
+        assert_eq!(code, "xyz");
+        assert_eq!(state.as_deref(), Some("abc"));
+        assert_eq!(parse_callback("xyz").unwrap(), (String::from("xyz"), None));
+        assert!(parse_callback("http://127.0.0.1:9090/cb?state=abc&error=access_denied").is_err());
+    }
+}
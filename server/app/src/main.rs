@@ -1,19 +1,22 @@
-use actix_web::web;
-use actix_web::web::{Data, Query};
-use actix_web::{App, HttpResponse, HttpServer, Responder};
+mod api;
+mod dashboard;
+
+use actix_web::web::{self, Data, Query};
+use actix_web::{middleware, App, HttpResponse, HttpResponseBuilder, HttpServer, Responder};
 use log::info;
 use serde_derive::Deserialize;
-use std::{fs, io};
-use subreddit_posts_logic::comment::delete_all_comments;
+use std::io;
+use subreddit_posts_logic::account::{migrate_legacy, Accounts};
+use subreddit_posts_logic::api_error::{
+    json_error_handler, path_error_handler, query_error_handler,
+};
+use subreddit_posts_logic::api_key::{require_api_key, ApiKey};
 use subreddit_posts_logic::data_store::DataStore;
-use subreddit_posts_logic::environment::Environment;
-use subreddit_posts_logic::flairs::retrieve_flairs_for;
-use subreddit_posts_logic::in_memory_data_store::InMemoryDataStore;
-use subreddit_posts_logic::login::{auth_token_for, request_login};
-use subreddit_posts_logic::post::{delete_with_upvotes_lt, post, Posts};
-use subreddit_posts_logic::reddit_client::AuthRedditClient;
-use subreddit_posts_logic::subreddit::get_all_from;
-use subreddit_posts_logic::{subreddit, user};
+use subreddit_posts_logic::environment::{AuthFlow, Environment};
+use subreddit_posts_logic::file_data_store::FileDataStore;
+use subreddit_posts_logic::job::Jobs;
+use subreddit_posts_logic::login::{auth_token_for, keep_logged_in, store_login};
+use subreddit_posts_logic::retention::RetentionRunner;
 
 #[actix_web::main]
 async fn main() -> io::Result<()> {
@@ -24,205 +27,106 @@ async fn main() -> io::Result<()> {
     info!("Starting server");
     let env = Environment::read_env();
     info!("Env {:?}", env);
+    let bind_address = (env.bind_address.clone(), env.port);
+    let api_key = Data::new(ApiKey::new(env.api_key.clone()));
+    if env.api_key.is_none() {
+        // printed rather than logged, so it shows up whatever RUST_LOG says
+        println!(
+            "No API_KEY set, the API key until the server stops is {}",
+            api_key.as_str()
+        );
+    }
 
-    let data = Data::new(InMemoryDataStore::new());
+    let data = Data::new(FileDataStore::new(&env.data_dir));
+    migrate_legacy(&env.data_dir, data.as_ref()).await;
+    let retention_runner = Data::new(RetentionRunner::new(&env.data_dir));
+    let accounts = Data::new(Accounts::new(&env.data_dir));
+    let jobs = Data::new(Jobs::new());
+
+    let runner = retention_runner.clone();
+    let store = data.clone();
+    let retained = accounts.clone();
+    actix_web::rt::spawn(async move {
+        runner
+            .run_periodically(
+                store.as_ref(),
+                retained.as_ref(),
+                &Environment::read_env().export_dir,
+            )
+            .await
+    });
+    let scheduler = accounts.clone();
+    let store = data.clone();
+    actix_web::rt::spawn(async move { scheduler.run_periodically(store.as_ref()).await });
+    if env.auth_flow == AuthFlow::Password {
+        let store = data.clone();
+        actix_web::rt::spawn(async move { keep_logged_in(store.as_ref()).await });
+    }
 
     HttpServer::new(move || {
         App::new()
+            .wrap(middleware::from_fn(require_api_key))
+            .app_data(api_key.clone())
             .app_data(data.clone())
-            .service(hello)
-            .service(login)
+            .app_data(retention_runner.clone())
+            .app_data(accounts.clone())
+            .app_data(jobs.clone())
+            .app_data(web::JsonConfig::default().error_handler(json_error_handler))
+            .app_data(web::QueryConfig::default().error_handler(query_error_handler))
+            .app_data(web::PathConfig::default().error_handler(path_error_handler))
+            .configure(dashboard::configure)
             .service(login_callback)
-            .service(upload)
-            .service(flairs)
-            .service(delete_comments)
-            .service(delete_posts)
-            .service(read_from_sub)
-            .service(delete_all_from_sub)
-            .service(delete_comments_from_sub)
+            .configure(api::configure)
+            .default_service(web::to(api::not_found))
     })
-    .bind("127.0.0.1:9090")?
+    .bind(bind_address)?
     .run()
     .await
 }
 
-#[actix_web::get("/")]
-async fn hello() -> impl Responder {
-    HttpResponse::Ok().body("Hello, world!")
-}
-
-#[actix_web::get("/reddit/login")]
-async fn login(data: Data<InMemoryDataStore>) -> impl Responder {
-    let settings = Environment::read_env();
-    info!("Calling login");
-    let login_request_id = request_login(settings).await;
-    data.store_login_request_id(login_request_id);
-
-    HttpResponse::Ok().body("Request sent")
-}
-
 #[actix_web::get("/reddit/login-callback")]
-async fn login_callback(
-    (params, data): (Query<Params>, Data<InMemoryDataStore>),
-) -> impl Responder {
-    info!("I was called");
-    info!("params {:?}", params);
+async fn login_callback((params, data): (Query<Params>, Data<FileDataStore>)) -> impl Responder {
+    info!("Login callback with {:?}", params);
 
-    let login_request_id = params
-        .state
-        .as_ref()
-        .expect("expect state as field, but it doesn't exist");
-    if data
-        .retrieve_login_request_id()
-        .ne(login_request_id.as_str())
-    {
-        panic!("Login request id are not same!!!")
+    if let Some(error) = &params.error {
+        return login_failed(&format!("Reddit refused the login: {}.", error));
+    }
+    let (Some(login_request_id), Some(code)) = (&params.state, &params.code) else {
+        return login_failed("The callback has no state or code.");
+    };
+    if !data.take_login_request_id(login_request_id) {
+        return login_failed("This login request is unknown or expired.");
     }
     let env = Environment::read_env();
-    let token = auth_token_for(
-        &params
-            .code
-            .clone()
-            .expect("Expect Code field, but it doesn't exist"),
-        env,
-    )
-    .await;
-    data.store_auth_token(token.access_token.clone());
-    HttpResponse::Ok().body("Ok")
-}
-
-#[actix_web::get("/reddit/post")]
-async fn upload(data: Data<InMemoryDataStore>) -> impl Responder {
-    let content = fs::read_to_string("server/.posts")
-        .or_else(|_| fs::read_to_string(".posts"))
-        .expect("Something went wrong reading the file with posts");
-
-    let auth_token = data.retrieve_auth_token();
-    let posts: Posts = serde_json::from_str(&content).expect("JSON was not well-formatted");
-    let client = AuthRedditClient::new(auth_token);
-    post(posts, &client).await;
-
-    try_post(".posts", &client).await;
-
-    HttpResponse::Ok().body("Uploaded")
-}
-
-async fn try_post(file_name: &str, client: &AuthRedditClient) {
-    let content = fs::read_to_string(format!("server/{}", file_name))
-        .or_else(|_| fs::read_to_string(file_name));
-    if content.is_ok() {
-        let content = content.unwrap();
-        let posts: Posts = serde_json::from_str(&content).expect("JSON was not well-formatted");
-        post(posts, client).await;
+    let account = match auth_token_for(code, env).await {
+        Ok(token) => store_login(data.as_ref(), token).await,
+        Err(error) => Err(error),
+    };
+    match account {
+        Ok(account) => login_page(
+            HttpResponse::Ok(),
+            "Logged in",
+            &format!("Logged in as {}, you can close this tab.", account),
+        ),
+        Err(error) => login_failed(&error.to_string()),
     }
 }
 
-#[actix_web::get("/reddit/comments/delete")]
-async fn delete_comments(data: Data<InMemoryDataStore>) -> impl Responder {
-    info!("Deleting all comments");
-
-    let auth_token = data.retrieve_auth_token();
-    let client = AuthRedditClient::new(auth_token);
-
-    let user = user::info(&client).await;
-    info!("user {:?}", user);
-
-    delete_all_comments(&client, &user).await;
-
-    info!("Deleted all comments");
-
-    HttpResponse::Ok().body("deleted")
+fn login_failed(reason: &str) -> HttpResponse {
+    login_page(HttpResponse::BadRequest(), "Login failed", reason)
 }
 
-#[actix_web::get("/reddit/posts/delete")]
-async fn delete_posts(data: Data<InMemoryDataStore>) -> impl Responder {
-    info!("Deleting post with ups < 5");
-
-    let auth_token = data.retrieve_auth_token();
-    let client = AuthRedditClient::new(auth_token);
-
-    let user = user::info(&client).await;
-    info!("user {:?}", user);
-
-    delete_with_upvotes_lt(&client, &user, 5).await;
-
-    info!("Deleted post with ups < 5");
-
-    HttpResponse::Ok().body("deleted")
-}
-
-#[actix_web::get("/reddit/sub/{sub_name}/info")]
-async fn read_from_sub(path: web::Path<String>, data: Data<InMemoryDataStore>) -> impl Responder {
-    let sub_name = path.into_inner();
-    info!("Getting info from {}", sub_name);
-    //https://www.reddit.com/r/[subreddit]/new.json?limit=100
-    let auth_token = data.retrieve_auth_token();
-    let client = AuthRedditClient::new(auth_token);
-
-    let user = user::info(&client).await;
-    info!("user {:?}", user);
-
-    subreddit::get_all_from(&client, &user, sub_name).await;
-
-    info!("Info received");
-
-    HttpResponse::Ok().body("retrieved")
-}
-
-#[actix_web::get("/reddit/sub/{sub_name}/info/delete/all")]
-async fn delete_all_from_sub(
-    path: web::Path<String>,
-    data: Data<InMemoryDataStore>,
-) -> impl Responder {
-    let sub_name = path.into_inner();
-    info!("Getting info from {}", sub_name);
-    //https://www.reddit.com/r/[subreddit]/new.json?limit=100
-    let auth_token = data.retrieve_auth_token();
-    let client = AuthRedditClient::new(auth_token);
-
-    let user = user::info(&client).await;
-    info!("user {:?}", user);
-
-    subreddit::delete_all_from(&client, &user, sub_name).await;
-
-    info!("Info received");
-
-    HttpResponse::Ok().body("retrieved")
-}
-
-#[actix_web::get("/reddit/sub/{sub_name}/info/delete/comments")]
-async fn delete_comments_from_sub(
-    path: web::Path<String>,
-    data: Data<InMemoryDataStore>,
-) -> impl Responder {
-    let sub_name = path.into_inner();
-    info!("Getting info from {}", sub_name);
-    //https://www.reddit.com/r/[subreddit]/new.json?limit=100
-    let auth_token = data.retrieve_auth_token();
-    let client = AuthRedditClient::new(auth_token);
-
-    let user = user::info(&client).await;
-    info!("user {:?}", user);
-
-    subreddit::delete_comments_from(&client, &user, sub_name).await;
-
-    info!("Info received");
-
-    HttpResponse::Ok().body("retrieved")
-}
-
-#[actix_web::get("/reddit/flairs")]
-async fn flairs(data: Data<InMemoryDataStore>) -> impl Responder {
-    let content = fs::read_to_string("server/.subreddits")
-        .or_else(|_| fs::read_to_string(".subreddits"))
-        .expect("Something went wrong reading the file with posts");
-
-    let client = AuthRedditClient::new(data.retrieve_auth_token());
-    let flair_info = retrieve_flairs_for(content.split(", ").collect(), &client).await;
-
-    info!("Retrieved flairs: {:?}", flair_info);
-
-    HttpResponse::Ok().body("retrieved")
+fn login_page(mut response: HttpResponseBuilder, heading: &str, text: &str) -> HttpResponse {
+    response
+        .content_type("text/html; charset=utf-8")
+        .body(format!(
+            "<html><body><h1>{}</h1><p>{}</p>\
+             <p><a href=\"/\">Back to the dashboard</a></p></body></html>",
+            heading,
+            text.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+        ))
 }
 
 #[derive(Debug, Deserialize)]
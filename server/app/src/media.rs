@@ -9,24 +9,6 @@ struct ImageData {
     pub mimetype: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct MediaResponse {
-    asset: Option<String>,
-    args: Vec<MediaArgs>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct MediaArgs {
-    action: String,
-    fields: Vec<MediaArgs>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct Field {
-    name: String,
-    value: String,
-}
-
 pub async fn upload_media(mime_prefix: &str, media_path: &str) -> (String, Option<String>) {
     let mime_types = HashMap::from([
         ("png", "image/png"),
@@ -38,10 +20,9 @@ pub async fn upload_media(mime_prefix: &str, media_path: &str) -> (String, Optio
     ]);
     let parts = media_path.split(".");
     let file_extension = parts.last().expect("No extension found");
-    let mime_type = *mime_types.get(file_extension).expect(&format!(
-        "Can't find mimy type for extension {}",
-        file_extension
-    ));
+    let mime_type = *mime_types
+        .get(file_extension)
+        .unwrap_or_else(|| panic!("Can't find mimy type for extension {}", file_extension));
     if !mime_type.starts_with(mime_prefix) {
         panic!(
             "Wrong file extension {} with expected mime type {}",
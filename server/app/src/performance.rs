@@ -0,0 +1,190 @@
+use crate::error::Result;
+use crate::post::{self, PostInfo};
+use crate::reddit_client::AuthRedditClient;
+use crate::stats::Distribution;
+use crate::user::User;
+use chrono::{DateTime, Timelike};
+use chrono_tz::Tz;
+use serde_derive::Serialize;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct GroupStats {
+    pub key: String,
+    pub posts: usize,
+    pub score: Distribution,
+    pub comments: Distribution,
+    pub upvote_ratio: f64,
+    pub removal_rate: f64,
+}
+
+impl GroupStats {
+    fn of(key: String, posts: &[&PostInfo]) -> GroupStats {
+        let count = posts.len();
+        let ratio_of = |value: f64| {
+            if count == 0 {
+                0.0
+            } else {
+                value / count as f64
+            }
+        };
+        GroupStats {
+            key,
+            posts: count,
+            score: Distribution::of(posts.iter().map(|post| post.score as f64)),
+            comments: Distribution::of(posts.iter().map(|post| post.num_comments as f64)),
+            upvote_ratio: ratio_of(posts.iter().map(|post| post.upvote_ratio).sum()),
+            removal_rate: ratio_of(posts.iter().filter(|post| post.is_removed()).count() as f64),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct PerformanceReport {
+    pub timezone: String,
+    pub overall: GroupStats,
+    pub by_subreddit: Vec<GroupStats>,
+    pub by_flair: Vec<GroupStats>,
+    pub by_hour: Vec<GroupStats>,
+}
+
+/// Flat representation of one group, used for the CSV export.
+#[derive(Serialize, Debug)]
+struct PerformanceRow<'a> {
+    group: &'a str,
+    key: &'a str,
+    posts: usize,
+    score_mean: f64,
+    score_median: f64,
+    score_p25: f64,
+    score_p75: f64,
+    score_p90: f64,
+    score_max: f64,
+    comments_mean: f64,
+    comments_median: f64,
+    upvote_ratio: f64,
+    removal_rate: f64,
+}
+
+impl PerformanceReport {
+    pub fn new(posts: &[PostInfo], tz: Tz) -> PerformanceReport {
+        let all: Vec<&PostInfo> = posts.iter().collect();
+        PerformanceReport {
+            timezone: tz.name().to_string(),
+            overall: GroupStats::of(String::from("all"), &all),
+            by_subreddit: group_by(posts, |post| post.subreddit.to_lowercase()),
+            by_flair: group_by(posts, |post| {
+                post.link_flair_text
+                    .clone()
+                    .unwrap_or_else(|| String::from("none"))
+            }),
+            by_hour: group_by(posts, |post| {
+                let hour = DateTime::from_timestamp(post.created_utc as i64, 0)
+                    .map(|created| created.with_timezone(&tz).hour())
+                    .unwrap_or_default();
+                format!("{:02}", hour)
+            }),
+        }
+    }
+
+    pub fn to_csv(&self) -> Result<String> {
+        let mut csv = csv::Writer::from_writer(Vec::new());
+        let groups = std::iter::once(("overall", &self.overall))
+            .chain(self.by_subreddit.iter().map(|stats| ("subreddit", stats)))
+            .chain(self.by_flair.iter().map(|stats| ("flair", stats)))
+            .chain(self.by_hour.iter().map(|stats| ("hour", stats)));
+        for (group, stats) in groups {
+            csv.serialize(PerformanceRow {
+                group,
+                key: stats.key.as_str(),
+                posts: stats.posts,
+                score_mean: stats.score.mean,
+                score_median: stats.score.median,
+                score_p25: stats.score.p25,
+                score_p75: stats.score.p75,
+                score_p90: stats.score.p90,
+                score_max: stats.score.max,
+                comments_mean: stats.comments.mean,
+                comments_median: stats.comments.median,
+                upvote_ratio: stats.upvote_ratio,
+                removal_rate: stats.removal_rate,
+            })?;
+        }
+        let bytes = csv
+            .into_inner()
+            .map_err(|error| std::io::Error::other(error.to_string()))?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+fn group_by(posts: &[PostInfo], key: impl Fn(&PostInfo) -> String) -> Vec<GroupStats> {
+    let mut groups: BTreeMap<String, Vec<&PostInfo>> = BTreeMap::new();
+    for post in posts {
+        groups.entry(key(post)).or_default().push(post);
+    }
+    groups
+        .into_iter()
+        .map(|(key, posts)| GroupStats::of(key, &posts))
+        .collect()
+}
+
+pub async fn performance_for(
+    client: &AuthRedditClient,
+    user: &User,
+    tz: Tz,
+) -> Result<PerformanceReport> {
+    let posts = post::retrieve_all_posts_with(client, user, |_| true).await?;
+    Ok(PerformanceReport::new(&posts, tz))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::post_info;
+    use chrono::Utc;
+
+    #[test]
+    fn posts_are_grouped_by_subreddit_flair_and_hour() {
+        let at = |seconds| DateTime::<Utc>::from_timestamp(seconds, 0).unwrap();
+        let posts = vec![
+            PostInfo {
+                num_comments: 2,
+                upvote_ratio: 0.9,
+                link_flair_text: Some(String::from("OC")),
+                ..post_info("a", "Art", at(3600), 10)
+            },
+            PostInfo {
+                upvote_ratio: 0.5,
+                removed_by_category: Some(String::from("moderator")),
+                ..post_info("b", "art", at(7200), 0)
+            },
+            PostInfo {
+                removed_by_category: Some(String::from("deleted")),
+                ..post_info("c", "pics", at(3700), 4)
+            },
+        ];
+
+        let report = PerformanceReport::new(&posts, Tz::UTC);
+
+        assert_eq!(report.overall.posts, 3);
+        let art = &report.by_subreddit[0];
+        assert_eq!(art.key, "art");
+        assert_eq!(art.posts, 2);
+        assert_eq!(art.score.median, 5.0);
+        assert!((art.upvote_ratio - 0.7).abs() < 1e-9);
+        assert_eq!(art.removal_rate, 0.5);
+        assert_eq!(report.by_subreddit[1].removal_rate, 0.0);
+        let flairs: Vec<&str> = report
+            .by_flair
+            .iter()
+            .map(|stats| stats.key.as_str())
+            .collect();
+        assert_eq!(flairs, vec!["OC", "none"]);
+        assert_eq!(report.by_hour[0].key, "01");
+        assert_eq!(report.by_hour[0].posts, 2);
+        let csv = report.to_csv().unwrap();
+        assert!(csv.contains("\nflair,OC,1,10.0,"));
+        assert!(csv.ends_with("\nhour,02,1,0.0,0.0,0.0,0.0,0.0,0.0,0.0,0.0,0.5,1.0\n"));
+    }
+}
@@ -1,18 +1,138 @@
+use crate::auto_schedule::{self, AutoScheduleSettings, ScheduleMode};
 use crate::comment::submit_comment;
+use crate::cooldown::{self, CooldownPolicy};
+use crate::duplicate::{self, DuplicateAction, DuplicateSettings};
+use crate::eligibility::{self, Ineligible, Requirements, SubredditAbout};
+use crate::error::{Error, Result};
+use crate::history::{History, Submission};
+use crate::job::{ItemStatus, Progress};
+use crate::listing::{self, Listing, Thing, UserListing};
 use crate::reddit_client::{AuthRedditClient, DeleteRequest};
-use crate::user::User;
+use crate::schedule::Schedule;
+use crate::user::{self, User};
 use crate::OAUTH_REDDIT_URL;
-use log::{debug, info};
+use chrono::{DateTime, Utc};
+use futures::{future, TryStreamExt};
+use log::{debug, error, info};
+use regex::Regex;
+use reqwest::Url;
 use serde_derive::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use utoipa::ToSchema;
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Longest title reddit accepts.
+const MAX_TITLE_LENGTH: usize = 300;
+const SUBREDDIT_NAME: &str = "^[A-Za-z0-9][A-Za-z0-9_]{1,20}$";
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct Posts {
     pub main_post_info: MainPostInfo,
     pub posts: Vec<Post>,
+    pub post_at: Option<DateTime<Utc>>,
+    pub recurrence: Option<Recurrence>,
+    pub schedule: Option<ScheduleMode>,
+    pub auto_schedule: Option<AutoScheduleSettings>,
+    pub duplicate_check: Option<DuplicateSettings>,
+}
+
+impl Posts {
+    /// Problems which would make the batch fail, empty when it can be submitted.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let main_post_info = &self.main_post_info;
+        if main_post_info.post_type != "link" {
+            problems.push(format!(
+                "post_type {} is not supported, only link",
+                main_post_info.post_type
+            ));
+        }
+        let recurrences = self
+            .posts
+            .iter()
+            .filter_map(|post| post.recurrence.as_ref())
+            .chain(&self.recurrence);
+        if recurrences
+            .into_iter()
+            .any(|recurrence| recurrence.check().is_err())
+        {
+            problems.push(String::from("recurrence every_hours must be above 0"));
+        }
+        let subreddit_name = Regex::new(SUBREDDIT_NAME).expect("valid subreddit name pattern");
+        let mut to_post = 0;
+        for post in self
+            .posts
+            .iter()
+            .filter(|post| post.need_to_be_posted.unwrap_or(true))
+        {
+            to_post += 1;
+            if !subreddit_name.is_match(&post.subreddit) {
+                problems.push(format!("{:?} is not a subreddit name", post.subreddit));
+            }
+            let body = post.body_override.as_ref().unwrap_or(&main_post_info.body);
+            if !Url::parse(body).is_ok_and(|url| matches!(url.scheme(), "http" | "https")) {
+                problems.push(format!(
+                    "{}: {:?} is not a http(s) URL",
+                    post.subreddit, body
+                ));
+            }
+            let title = FinalPost::new(main_post_info, post, String::new()).title;
+            if title.trim().is_empty() || title.chars().count() > MAX_TITLE_LENGTH {
+                problems.push(format!(
+                    "{}: title must have 1 to {} characters",
+                    post.subreddit, MAX_TITLE_LENGTH
+                ));
+            }
+        }
+        if to_post == 0 {
+            problems.push(String::from("there are no posts to submit"));
+        }
+        problems
+    }
+
+    /// `failed` results of the entries to post, for when the batch can't be submitted at all.
+    pub fn failed(&self) -> Vec<SubmissionResult> {
+        self.posts
+            .iter()
+            .filter(|post| post.need_to_be_posted.unwrap_or(true))
+            .map(|post| {
+                let url = post
+                    .body_override
+                    .clone()
+                    .unwrap_or(self.main_post_info.body.clone());
+                let post = FinalPost::new(&self.main_post_info, post, url);
+                SubmissionResult::new(&post, SubmissionStatus::Failed, Vec::new())
+            })
+            .collect()
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Reads the posts files, `server/{file}` first like the other config files, a file listed twice
+/// is only read once.
+pub fn read_posts_files(files: &[String]) -> Result<Vec<Posts>> {
+    let mut seen = Vec::new();
+    let mut batches = Vec::new();
+    for file in files {
+        let path = [format!("server/{}", file), file.clone()]
+            .into_iter()
+            .map(PathBuf::from)
+            .find(|path| path.is_file())
+            .ok_or_else(|| Error::Config(format!("posts file {} not found", file)))?;
+        let path = path.canonicalize()?;
+        if seen.contains(&path) {
+            info!("Posts file {:?} is listed twice, reading it once", path);
+            continue;
+        }
+        let content = fs::read_to_string(&path)?;
+        batches.push(serde_json::from_str(&content)?);
+        seen.push(path);
+    }
+    Ok(batches)
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct MainPostInfo {
     pub post_type: String,
     pub body: String,
@@ -21,7 +141,7 @@ pub struct MainPostInfo {
     pub comment: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct Post {
     pub subreddit: String,
     pub body_override: Option<String>,
@@ -31,22 +151,47 @@ pub struct Post {
     pub flair_name: Option<String>,
     pub need_to_be_posted: Option<bool>,
     pub comment: Option<String>,
+    pub post_at: Option<DateTime<Utc>>,
+    pub recurrence: Option<Recurrence>,
+    /// Overrides the cooldown of the subreddit from `.cooldowns`.
+    pub cooldown_days: Option<u32>,
+    #[serde(flatten)]
+    pub requirements: Requirements,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct FinalPost {
-    sr: String,
-    resubmit: bool,
-    sendreplies: bool,
-    title: String,
-    nsfw: bool,
-    spoiler: bool,
-    subreddit: String,
-    flair_id: Option<String>,
-    flair_name: Option<String>,
-    url: String,
-    kind: String,
-    comment: Option<String>,
+/// Repeats a scheduled post every `every_hours`, `times` limits the number of submissions.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct Recurrence {
+    pub every_hours: u64,
+    pub times: Option<u32>,
+}
+
+impl Recurrence {
+    /// A recurrence of 0 hours would be submitted on every tick of the scheduler.
+    pub fn check(&self) -> Result<()> {
+        if self.every_hours == 0 {
+            return Err(Error::Config(String::from(
+                "recurrence every_hours must be above 0",
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct FinalPost {
+    pub sr: String,
+    pub resubmit: bool,
+    pub sendreplies: bool,
+    pub title: String,
+    pub nsfw: bool,
+    pub spoiler: bool,
+    pub subreddit: String,
+    pub flair_id: Option<String>,
+    pub flair_name: Option<String>,
+    pub url: String,
+    pub kind: String,
+    pub comment: Option<String>,
 }
 
 impl FinalPost {
@@ -82,75 +227,340 @@ impl FinalPost {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct PostInfo {
     pub id: String,
-    pub upvotes: u64,
+    #[serde(alias = "ups")]
+    pub upvotes: i64,
     pub name: String,
     pub subreddit: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub permalink: String,
+    #[serde(default)]
+    pub selftext: String,
+    pub url: Option<String>,
+    #[serde(default)]
+    pub score: i64,
+    /// Required, retention deletes by age and must not take a missing time for 1970.
+    pub created_utc: f64,
+    pub link_flair_text: Option<String>,
+    #[serde(default)]
+    pub num_comments: u64,
+    #[serde(default)]
+    pub upvote_ratio: f64,
+    pub removed_by_category: Option<String>,
+    /// Name of the moderator who removed the post, or `true` when it is hidden from us.
+    pub banned_by: Option<Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct PostInfos {
-    after: Option<String>,
-    posts: Vec<PostInfo>,
+impl PostInfo {
+    /// Removed by moderators, automod or reddit itself, deletion by the author doesn't count.
+    pub fn is_removed(&self) -> bool {
+        self.removed_by_category
+            .as_ref()
+            .is_some_and(|category| category != "deleted")
+    }
 }
 
-pub async fn post(posts: Posts, client: &AuthRedditClient) {
-    let final_posts: Vec<FinalPost> = create_final_posts(posts);
-    info!("Final posts: {:?}", final_posts);
-    for post in final_posts {
-        let full_ulr = submit_post(&client, &post).await;
-        info!("post url is {:?}", full_ulr);
+/// Final post together with the time it should be submitted at, `None` means right away.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct PlannedPost {
+    pub post: FinalPost,
+    pub post_at: Option<DateTime<Utc>>,
+    pub recurrence: Option<Recurrence>,
+    pub cooldown_days: Option<u32>,
+    pub requirements: Requirements,
+}
 
-        if post.comment.is_some() && full_ulr.is_some() {
-            submit_comment(&client, post.comment.clone().unwrap(), full_ulr.unwrap()).await;
-        }
+/// What `post` is going to do, also returned as the dry-run.
+#[derive(Serialize, Debug, Default, ToSchema)]
+pub struct Plan {
+    pub planned: Vec<PlannedPost>,
+    pub skipped: Vec<SubmissionResult>,
+    pub ineligible: Vec<Ineligible>,
+}
+
+impl Plan {
+    /// Adds the plan of another batch.
+    pub fn extend(&mut self, other: Plan) {
+        self.planned.extend(other.planned);
+        self.skipped.extend(other.skipped);
+        self.ineligible.extend(other.ineligible);
     }
 }
 
-pub async fn delete_with_upvotes_lt(client: &AuthRedditClient, user: &User, min_votes: u64) {
-    let posts = retrieve_all_posts_with(client, user, |post| post.upvotes < min_votes).await;
-    for post in posts {
-        let delete_request = DeleteRequest::new_json(post.name.as_str());
-        info!("Will delete {:?}", post);
-        client.delete(&delete_request).await;
+#[derive(Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase", tag = "status")]
+pub enum SubmissionStatus {
+    Posted { id: String },
+    Scheduled { id: String, post_at: DateTime<Utc> },
+    Skipped { reason: String },
+    Failed,
+}
+
+/// Outcome of one final post, the submission report is a list of them.
+#[derive(Serialize, Debug, ToSchema)]
+pub struct SubmissionResult {
+    pub subreddit: String,
+    pub title: String,
+    pub url: String,
+    #[serde(flatten)]
+    pub status: SubmissionStatus,
+    pub warnings: Vec<String>,
+}
+
+impl SubmissionResult {
+    fn new(post: &FinalPost, status: SubmissionStatus, warnings: Vec<String>) -> SubmissionResult {
+        SubmissionResult {
+            subreddit: post.subreddit.clone(),
+            title: post.title.clone(),
+            url: post.url.clone(),
+            status,
+            warnings,
+        }
     }
-    info!("Posts deleted");
 }
 
-pub async fn retrieve_all_posts_with(
+pub async fn post(
+    posts: Posts,
     client: &AuthRedditClient,
-    user: &User,
-    filter: impl Fn(&PostInfo) -> bool,
-) -> Vec<PostInfo> {
-    let mut after: Option<String> = None;
-    let mut result: Vec<PostInfo> = Vec::new();
-    loop {
-        let posts = retrieve_all_posts(client, user, after.clone()).await;
-        info!("Retrieved {} posts", posts.posts.len());
-
-        after = posts.after.clone();
-        if posts.posts.len().eq(&0) {
+    schedule: &Schedule,
+    history: &History,
+    cooldowns: &CooldownPolicy,
+    progress: &impl Progress,
+) -> Result<Vec<SubmissionResult>> {
+    let duplicate_check = posts.duplicate_check.clone().unwrap_or_default();
+    let plan = plan(posts, client, schedule, history, cooldowns).await?;
+    info!("Final posts: {:?}", plan.planned);
+    let skipped = progress.add_items(
+        plan.skipped
+            .iter()
+            .map(|result| result.subreddit.clone())
+            .collect(),
+    );
+    for (index, result) in plan.skipped.iter().enumerate() {
+        progress.finish_item(skipped + index, item_status(&result.status), None);
+    }
+    let first = progress.add_items(
+        plan.planned
+            .iter()
+            .map(|planned| planned.post.subreddit.clone())
+            .collect(),
+    );
+    let mut report = plan.skipped;
+    for (index, planned) in plan.planned.into_iter().enumerate() {
+        if progress.is_cancelled() {
+            info!("Posting cancelled");
             break;
         }
-        for post in posts.posts {
-            if after.is_some() {
-                let value = after.clone().unwrap();
-                if value.eq(&post.name) {
-                    continue;
-                }
+        let result = match planned.post_at {
+            Some(post_at) => schedule
+                .add(
+                    planned.post.clone(),
+                    post_at,
+                    planned.recurrence,
+                    Some(duplicate_check.clone()),
+                    planned.cooldown_days,
+                )
+                .map(|scheduled| {
+                    info!("Scheduled {:?}", scheduled);
+                    let status = SubmissionStatus::Scheduled {
+                        id: scheduled.id,
+                        post_at,
+                    };
+                    SubmissionResult::new(&planned.post, status, Vec::new())
+                }),
+            None => publish(client, &planned.post, history, &duplicate_check, &[]).await,
+        };
+        // the posts before this one are submitted already, their results must not get lost
+        let result = result.unwrap_or_else(|error| {
+            error!("Can't submit {:?}: {}", planned.post, error);
+            let warnings = vec![error.to_string()];
+            SubmissionResult::new(&planned.post, SubmissionStatus::Failed, warnings)
+        });
+        let detail = match &result.status {
+            SubmissionStatus::Posted { id } => Some(id.clone()),
+            SubmissionStatus::Scheduled { post_at, .. } => Some(format!("scheduled {}", post_at)),
+            SubmissionStatus::Skipped { reason } => Some(reason.clone()),
+            SubmissionStatus::Failed => None,
+        };
+        progress.finish_item(first + index, item_status(&result.status), detail);
+        report.push(result);
+    }
+    Ok(report)
+}
+
+fn item_status(status: &SubmissionStatus) -> ItemStatus {
+    match status {
+        SubmissionStatus::Posted { .. } | SubmissionStatus::Scheduled { .. } => ItemStatus::Done,
+        SubmissionStatus::Skipped { .. } => ItemStatus::Skipped,
+        SubmissionStatus::Failed => ItemStatus::Failed,
+    }
+}
+
+/// Creates final posts, skips the ones inside a cooldown or to subreddits we can't post to and
+/// assigns slots to auto scheduled ones, without submitting anything.
+pub async fn plan(
+    posts: Posts,
+    client: &AuthRedditClient,
+    schedule: &Schedule,
+    history: &History,
+    cooldowns: &CooldownPolicy,
+) -> Result<Plan> {
+    let auto_schedule = match posts.schedule {
+        Some(ScheduleMode::Auto) => Some(posts.auto_schedule.clone().unwrap_or_default()),
+        None => None,
+    };
+    let now = Utc::now();
+    let (final_posts, mut skipped) = create_final_posts(posts, &history.list(), cooldowns, now);
+
+    let user = user::me(client).await?;
+    let mut abouts: HashMap<String, Result<SubredditAbout>> = HashMap::new();
+    let mut planned_posts = Vec::new();
+    let mut ineligible: Vec<Ineligible> = Vec::new();
+    for planned in final_posts {
+        let subreddit = planned.post.subreddit.to_lowercase();
+        if !abouts.contains_key(&subreddit) {
+            let about = eligibility::about(client, &subreddit).await;
+            abouts.insert(subreddit.clone(), about);
+        }
+        let reasons = match &abouts[&subreddit] {
+            Ok(about) => {
+                eligibility::check(about, &user, &planned.post.kind, &planned.requirements, now)
             }
-            if filter(&post) {
-                result.push(post);
+            Err(error) => vec![format!("can't read the subreddit: {}", error)],
+        };
+        if reasons.is_empty() {
+            planned_posts.push(planned);
+            continue;
+        }
+        info!("Can't post to {}: {:?}", planned.post.subreddit, reasons);
+        let status = SubmissionStatus::Skipped {
+            reason: reasons.join(", "),
+        };
+        skipped.push(SubmissionResult::new(&planned.post, status, Vec::new()));
+        if !ineligible
+            .iter()
+            .any(|known| known.subreddit.eq_ignore_ascii_case(&subreddit))
+        {
+            ineligible.push(Ineligible {
+                subreddit: planned.post.subreddit.clone(),
+                reasons,
+            });
+        }
+    }
+
+    if let Some(settings) = auto_schedule {
+        auto_schedule::assign_slots(client, &mut planned_posts, &settings, schedule).await?;
+    }
+    Ok(Plan {
+        planned: planned_posts,
+        skipped,
+        ineligible,
+    })
+}
+
+/// Submits the post and its comment and records it in the history, unless the same URL was
+/// recently posted to the subreddit and `duplicate_check` says to skip it. `own_ids` are the
+/// earlier occurrences of a recurring post, which are never duplicates.
+pub async fn publish(
+    client: &AuthRedditClient,
+    post: &FinalPost,
+    history: &History,
+    duplicate_check: &DuplicateSettings,
+    own_ids: &[String],
+) -> Result<SubmissionResult> {
+    let mut post = post.clone();
+    let mut warnings = Vec::new();
+    let duplicate =
+        duplicate::find_duplicate(client, &history.list(), &post, duplicate_check, own_ids).await?;
+    if let Some(duplicate) = duplicate {
+        info!("Duplicate of {:?}: {}", post, duplicate);
+        match duplicate_check.action {
+            DuplicateAction::Skip => {
+                let status = SubmissionStatus::Skipped {
+                    reason: duplicate.to_string(),
+                };
+                return Ok(SubmissionResult::new(&post, status, warnings));
+            }
+            DuplicateAction::Warn => {
+                warnings.push(duplicate.to_string());
+                post.resubmit = true;
             }
         }
+    }
 
-        if after.is_none() {
-            break;
+    let full_ulr = submit_post(client, &post).await;
+    info!("post url is {:?}", full_ulr);
+
+    if let Some(id) = full_ulr.clone() {
+        // the post is live already, failing here would make the caller submit it again
+        if let Err(record_error) = history.record(Submission::new(id.clone(), &post, Utc::now())) {
+            error!("Can't record {} in the history: {}", id, record_error);
+            warnings.push(format!("not recorded in the history: {}", record_error));
+        }
+    }
+    if let (Some(comment), Some(full_ulr)) = (post.comment.clone(), full_ulr.clone()) {
+        submit_comment(client, comment, full_ulr).await;
+    }
+    let status = match full_ulr {
+        Some(id) => SubmissionStatus::Posted { id },
+        None => SubmissionStatus::Failed,
+    };
+    Ok(SubmissionResult::new(&post, status, warnings))
+}
+
+/// Current state of posts by their fullnames (`t3_...`), at most 100 per request.
+pub async fn info_by_names(client: &AuthRedditClient, names: &[String]) -> Result<Vec<PostInfo>> {
+    let mut result = Vec::new();
+    for chunk in names.chunks(100) {
+        let url = format!("{}/api/info?id={}", OAUTH_REDDIT_URL, chunk.join(","));
+        let listing: Thing<Listing<Thing<PostInfo>>> = client.get_json(url.as_str()).await?;
+        result.extend(listing.data.children.into_iter().map(|thing| thing.data));
+    }
+    Ok(result)
+}
+
+pub async fn delete_with_upvotes_lt(
+    client: &AuthRedditClient,
+    user: &User,
+    min_votes: i64,
+    progress: &impl Progress,
+) -> Result<()> {
+    let posts = retrieve_all_posts_with(client, user, |post| post.upvotes < min_votes).await?;
+    delete_posts(client, &posts, progress).await;
+    info!("Posts deleted");
+    Ok(())
+}
+
+/// Deletes the posts one by one until `progress` is cancelled.
+pub async fn delete_posts(client: &AuthRedditClient, posts: &[PostInfo], progress: &impl Progress) {
+    let first = progress.add_items(posts.iter().map(|post| post.name.clone()).collect());
+    for (index, post) in posts.iter().enumerate() {
+        if progress.is_cancelled() {
+            info!("Deleting posts cancelled");
+            return;
         }
+        let delete_request = DeleteRequest::new_json(post.name.as_str());
+        info!("Will delete {:?}", post);
+        client.delete(&delete_request).await;
+        progress.finish_item(first + index, ItemStatus::Done, None);
     }
-    return result;
+}
+
+pub async fn retrieve_all_posts_with(
+    client: &AuthRedditClient,
+    user: &User,
+    filter: impl Fn(&PostInfo) -> bool,
+) -> Result<Vec<PostInfo>> {
+    let posts: Vec<PostInfo> = listing::paginate(client, UserListing::Submitted.url(user))
+        .try_filter(|post| future::ready(filter(post)))
+        .try_collect()
+        .await?;
+    info!("Retrieved {} posts", posts.len());
+    Ok(posts)
 }
 
 async fn submit_post(client: &AuthRedditClient, post: &FinalPost) -> Option<String> {
@@ -167,11 +577,7 @@ async fn submit_post(client: &AuthRedditClient, post: &FinalPost) -> Option<Stri
 
 fn retrieve_post_url(post: &FinalPost, body: String) -> Option<String> {
     let json: Value = serde_json::from_str(body.as_str()).expect("Json format expected");
-    let value = json.get("jquery");
-    if value.is_none() {
-        return None;
-    }
-    let value = value.expect("jquery field");
+    let value = json.get("jquery")?;
     let values = value.as_array().expect("some values");
     for value in values {
         let subvalues = value.as_array().expect("some values");
@@ -183,7 +589,7 @@ fn retrieve_post_url(post: &FinalPost, body: String) -> Option<String> {
                 continue;
             }
             let possible_value = possible_value.unwrap();
-            if possible_value.len() == 0 {
+            if possible_value.is_empty() {
                 continue;
             }
             let possible_value = possible_value[0].as_str().unwrap_or("");
@@ -200,66 +606,119 @@ fn retrieve_post_url(post: &FinalPost, body: String) -> Option<String> {
 
 fn retrieve_post_id(post_url: String) -> Option<String> {
     let parts: Vec<&str> = post_url.split("/").collect();
-    let comment_position = parts.iter().position(|part| *part == "comments");
-    if comment_position.is_none() {
-        return None;
-    }
-    let id_position = comment_position.unwrap() + 1;
+    let comment_position = parts.iter().position(|part| *part == "comments")?;
+    let id_position = comment_position + 1;
 
     parts.get(id_position).map(|id| String::from(*id))
 }
 
-fn create_final_posts(posts: Posts) -> Vec<FinalPost> {
-    posts
+/// Planned posts, and skipped results for entries still inside the cooldown of their subreddit.
+fn create_final_posts(
+    posts: Posts,
+    submissions: &[Submission],
+    cooldowns: &CooldownPolicy,
+    now: DateTime<Utc>,
+) -> (Vec<PlannedPost>, Vec<SubmissionResult>) {
+    let mut planned_posts = Vec::new();
+    let mut skipped = Vec::new();
+    for post in posts
         .posts
         .iter()
         .filter(|value| value.need_to_be_posted.unwrap_or(true))
-        .map(|post| {
-            let body = post
-                .body_override
-                .clone()
-                .unwrap_or(posts.main_post_info.body.clone());
-            let url = match posts.main_post_info.post_type.as_str() {
-                "link" => body,
-                _ => panic!("Unsupported post type"),
-            };
-            FinalPost::new(&posts.main_post_info, post, url)
-        })
-        .collect()
-}
-
-async fn retrieve_all_posts(
-    client: &AuthRedditClient,
-    user: &User,
-    after: Option<String>,
-) -> PostInfos {
-    let mut url = format!(
-        "{}{}submitted?limit=1000",
-        OAUTH_REDDIT_URL,
-        user.url.as_str()
-    );
-    if after.is_some() {
-        url = format!("{}&after={}", url, after.unwrap());
+    {
+        let body = post
+            .body_override
+            .clone()
+            .unwrap_or(posts.main_post_info.body.clone());
+        let url = match posts.main_post_info.post_type.as_str() {
+            "link" => body,
+            _ => panic!("Unsupported post type"),
+        };
+        let planned = PlannedPost {
+            post: FinalPost::new(&posts.main_post_info, post, url),
+            post_at: post.post_at.or(posts.post_at),
+            recurrence: post.recurrence.clone().or(posts.recurrence.clone()),
+            cooldown_days: post.cooldown_days,
+            requirements: post.requirements.clone(),
+        };
+        let reason = post
+            .cooldown_days
+            .or_else(|| cooldowns.days_for(&post.subreddit))
+            .and_then(|days| {
+                cooldown::check(
+                    submissions,
+                    &planned.post.subreddit,
+                    &planned.post.url,
+                    days,
+                    planned.post_at.unwrap_or(now),
+                )
+            });
+        match reason {
+            Some(reason) => {
+                info!("Skipping {:?}: {}", planned.post, reason);
+                let status = SubmissionStatus::Skipped { reason };
+                skipped.push(SubmissionResult::new(&planned.post, status, Vec::new()));
+            }
+            None => planned_posts.push(planned),
+        }
     }
-    let body = client.get(url.as_str()).await;
+    (planned_posts, skipped)
+}
 
-    let value: Value = serde_json::from_str(body.as_str()).expect("Json format expected");
-    debug!("The json value is {}", value);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let after = value["data"]["after"].as_str();
+    #[test]
+    fn invalid_batches_are_reported() {
+        let posts: Posts = serde_json::from_value(serde_json::json!({
+            "main_post_info": {
+                "post_type": "link", "body": "https://example.com/pic.jpeg", "title": "title",
+                "nsfw": false
+            },
+            "posts": [
+                {"subreddit": "pics"},
+                {"subreddit": "r/art", "body_override": "pic.jpeg"},
+                {"subreddit": "skipped", "need_to_be_posted": false, "title_override": ""}
+            ],
+            "recurrence": {"every_hours": 0}
+        }))
+        .unwrap();
 
-    let posts = value["data"]["children"].as_array().unwrap();
-    let posts: Vec<PostInfo> = posts
-        .iter()
-        .map(|post| PostInfo {
-            id: String::from(post["data"]["id"].as_str().unwrap()),
-            upvotes: post["data"]["ups"].as_u64().unwrap(),
-            name: String::from(post["data"]["name"].as_str().unwrap()),
-            subreddit: String::from(post["data"]["subreddit"].as_str().unwrap()),
-        })
-        .collect();
-    PostInfos {
-        after: after.map(|val| String::from(val)),
-        posts,
+        assert_eq!(
+            posts.validate(),
+            vec![
+                "recurrence every_hours must be above 0",
+                "\"r/art\" is not a subreddit name",
+                "r/art: \"pic.jpeg\" is not a http(s) URL"
+            ]
+        );
+        let failed: Vec<(String, String)> = posts
+            .failed()
+            .into_iter()
+            .map(|result| (result.subreddit, result.url))
+            .collect();
+        assert_eq!(
+            failed,
+            vec![
+                (
+                    String::from("pics"),
+                    String::from("https://example.com/pic.jpeg")
+                ),
+                (String::from("r/art"), String::from("pic.jpeg"))
+            ]
+        );
+        let empty: Posts = serde_json::from_value(serde_json::json!({
+            "main_post_info": {"post_type": "self", "body": "", "title": "", "nsfw": false},
+            "posts": []
+        }))
+        .unwrap();
+        assert_eq!(
+            empty.validate(),
+            vec![
+                "post_type self is not supported, only link",
+                "there are no posts to submit"
+            ]
+        );
     }
 }
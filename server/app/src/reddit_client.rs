@@ -1,42 +1,132 @@
+use crate::error::{Error, Result};
 use crate::OAUTH_REDDIT_URL;
 use log::{debug, info};
-use reqwest::{Client, RequestBuilder};
+use reqwest::header::HeaderMap;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
 use serde::Serialize;
-use serde_derive::{Deserialize, Serialize};
-use std::{thread, time};
+use serde_derive::Deserialize;
+use std::sync::{Arc, Mutex};
+use std::time;
+use std::time::Instant;
+
+/// Requests an account has left until reddit resets its window, read from the
+/// `x-ratelimit-remaining` and `x-ratelimit-reset` headers of every response.
+#[derive(Debug, Default)]
+pub struct RateBudget {
+    window: Mutex<Option<(f64, Instant)>>,
+}
+
+impl RateBudget {
+    pub fn new() -> RateBudget {
+        RateBudget::default()
+    }
+
+    fn update(&self, headers: &HeaderMap) {
+        let header = |name: &str| -> Option<f64> { headers.get(name)?.to_str().ok()?.parse().ok() };
+        // a negative, NaN or huge reset is ignored rather than trusted
+        let reset_at = header("x-ratelimit-reset")
+            .and_then(|reset| time::Duration::try_from_secs_f64(reset).ok())
+            .and_then(|reset| Instant::now().checked_add(reset));
+        if let (Some(remaining), Some(reset_at)) = (header("x-ratelimit-remaining"), reset_at) {
+            *self
+                .window
+                .lock()
+                .expect("can't obtain the lock to update rate budget") =
+                Some((remaining, reset_at));
+        }
+    }
+
+    /// How long to wait before the next request, zero while there are requests left.
+    pub fn wait_time(&self, now: Instant) -> time::Duration {
+        match *self
+            .window
+            .lock()
+            .expect("can't obtain the lock to read rate budget")
+        {
+            Some((remaining, reset_at)) if remaining < 1.0 => {
+                reset_at.saturating_duration_since(now)
+            }
+            _ => time::Duration::ZERO,
+        }
+    }
+
+    async fn wait(&self) {
+        let wait_time = self.wait_time(Instant::now());
+        if !wait_time.is_zero() {
+            info!("Rate limit used up, waiting {:?}", wait_time);
+            actix_web::rt::time::sleep(wait_time).await;
+        }
+    }
+}
 
 pub struct AuthRedditClient {
     client: Client,
     auth_token: String,
+    budget: Arc<RateBudget>,
 }
 
 impl AuthRedditClient {
     pub fn new(auth_token: String) -> AuthRedditClient {
+        AuthRedditClient::with_budget(auth_token, Arc::new(RateBudget::new()))
+    }
+
+    /// Client sharing the rate limit budget of its account with the other clients of the account.
+    pub fn with_budget(auth_token: String, budget: Arc<RateBudget>) -> AuthRedditClient {
         let client = reqwest::Client::builder()
             .build()
             .expect("error during client build");
 
-        AuthRedditClient { client, auth_token }
+        AuthRedditClient {
+            client,
+            auth_token,
+            budget,
+        }
     }
 
-    pub async fn get(&self, url: &str) -> String {
+    pub async fn try_get(&self, url: &str) -> Result<String> {
+        self.budget.wait().await;
         let result = Self::add_headers(self.client.get(url).bearer_auth(self.auth_token.as_str()))
             .send()
             .await;
         debug!("Result body of GET {},  is {:?}", url, result);
 
-        let body = result
-            .expect("Result is empty")
-            .text()
-            .await
-            .expect("Body is empty");
+        let response = result?;
+        self.budget.update(response.headers());
+        let status = response.status();
+        let body = response.text().await?;
         debug!("Result body of GET {},  is {:?}", url, body);
 
         // limit 60 post in a second
         let ten_millis = time::Duration::from_millis(21);
-        thread::sleep(ten_millis);
+        actix_web::rt::time::sleep(ten_millis).await;
+
+        if !status.is_success() {
+            return Err(Error::Reddit(format!(
+                "GET {} returned {}: {}",
+                url, status, body
+            )));
+        }
+        Ok(body)
+    }
+
+    pub async fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
+        let body = self.try_get(url).await?;
+        Ok(serde_json::from_str(body.as_str())?)
+    }
+
+    /// GET as a logged-out visitor, without the bearer token, returns the status and the body.
+    pub async fn get_public(&self, url: &str) -> Result<(StatusCode, String)> {
+        let response = Self::add_headers(self.client.get(url)).send().await?;
+        let status = response.status();
+        let body = response.text().await?;
+        debug!("Result of public GET {} is {} {:?}", url, status, body);
 
-        return body;
+        // logged-out requests have a much lower limit
+        let sleep_time = time::Duration::from_millis(1_000);
+        actix_web::rt::time::sleep(sleep_time).await;
+
+        Ok((status, body))
     }
 
     pub async fn post<T: Serialize>(&self, url: &str, body: Option<T>) -> String {
@@ -50,21 +140,20 @@ impl AuthRedditClient {
             None => post_request_builder,
         };
 
+        self.budget.wait().await;
         let result = post_request_builder.send().await;
         debug!("Result body of POST {},  is {:?}", url, result);
 
-        let body = result
-            .expect("Result is empty")
-            .text()
-            .await
-            .expect("Body is empty");
+        let response = result.expect("Result is empty");
+        self.budget.update(response.headers());
+        let body = response.text().await.expect("Body is empty");
         debug!("Result body of POST {},  is {:?}", url, body);
 
         // limit 60 post in a second
         let sleep_time = time::Duration::from_millis(30_000);
-        thread::sleep(sleep_time);
+        actix_web::rt::time::sleep(sleep_time).await;
 
-        return body;
+        body
     }
 
     pub async fn delete(&self, delete_request: &DeleteRequest) {
@@ -87,9 +176,10 @@ impl AuthRedditClient {
             return;
         }
         let url = format!("{}/api/del", OAUTH_REDDIT_URL);
+        self.budget.wait().await;
         let result = Self::add_headers(self.client.post(url).bearer_auth(self.auth_token.as_str()))
             .body(
-                serde_urlencoded::to_string(&delete_request)
+                serde_urlencoded::to_string(delete_request)
                     .expect("serialize issue during obtain auth token"),
             )
             .send()
@@ -97,16 +187,14 @@ impl AuthRedditClient {
 
         debug!("Result of deletion is {:?}", result);
 
-        let body = result
-            .expect("Result is empty")
-            .text()
-            .await
-            .expect("Body is empty");
+        let response = result.expect("Result is empty");
+        self.budget.update(response.headers());
+        let body = response.text().await.expect("Body is empty");
         debug!("Result of deletion is {:?}", body);
 
         // limit 60 post in a second
         let ten_millis = time::Duration::from_millis(21);
-        thread::sleep(ten_millis);
+        actix_web::rt::time::sleep(ten_millis).await;
     }
 
     pub fn add_headers(builder: RequestBuilder) -> RequestBuilder {
@@ -134,3 +222,31 @@ impl DeleteRequest {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn budget_waits_only_when_used_up() {
+        let budget = RateBudget::new();
+        assert!(budget.wait_time(Instant::now()).is_zero());
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("5.0"));
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("120"));
+        budget.update(&headers);
+        assert!(budget.wait_time(Instant::now()).is_zero());
+
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0.0"));
+        budget.update(&headers);
+        assert!(budget.wait_time(Instant::now()) > time::Duration::from_secs(100));
+
+        for reset in ["-1", "NaN", "1e300"] {
+            headers.insert("x-ratelimit-reset", HeaderValue::from_static(reset));
+            budget.update(&headers);
+        }
+        assert!(budget.wait_time(Instant::now()) > time::Duration::from_secs(100));
+    }
+}
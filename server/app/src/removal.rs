@@ -0,0 +1,176 @@
+use crate::error::{Error, Result};
+use crate::listing::{Listing, Thing};
+use crate::post::{self, PostInfo};
+use crate::reddit_client::AuthRedditClient;
+use crate::user::User;
+use crate::REDDIT_URL;
+use log::{info, warn};
+use reqwest::StatusCode;
+use serde_derive::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum RemovalState {
+    Live,
+    ModRemoved,
+    SpamFiltered,
+    Deleted,
+    /// Looks live to us but the logged-out check failed, e.g. because of rate limiting.
+    Unknown,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct PostRemoval {
+    pub id: String,
+    pub subreddit: String,
+    pub title: String,
+    pub permalink: String,
+    pub state: RemovalState,
+    pub removed_by_category: Option<String>,
+    /// `None` when the logged-out check wasn't needed because the post is already removed, or
+    /// when it failed and the state is unknown.
+    pub publicly_visible: Option<bool>,
+}
+
+#[derive(Serialize, Debug, Default, ToSchema)]
+pub struct SubredditRemovals {
+    pub subreddit: String,
+    pub live: usize,
+    pub mod_removed: usize,
+    pub spam_filtered: usize,
+    pub deleted: usize,
+    pub unknown: usize,
+    pub posts: Vec<PostRemoval>,
+}
+
+/// State of the post as seen by us, `publicly_visible` is what logged-out visitors see.
+pub fn classify(post: &PostInfo, publicly_visible: Option<bool>) -> RemovalState {
+    match post.removed_by_category.as_deref() {
+        Some("deleted") | Some("author") => return RemovalState::Deleted,
+        Some("reddit") | Some("automod_filtered") => return RemovalState::SpamFiltered,
+        Some(_) => return RemovalState::ModRemoved,
+        None => {}
+    }
+    match &post.banned_by {
+        None | Some(Value::Null) | Some(Value::Bool(false)) => {}
+        Some(_) => return RemovalState::ModRemoved,
+    }
+    match publicly_visible {
+        Some(false) => RemovalState::SpamFiltered,
+        _ => RemovalState::Live,
+    }
+}
+
+/// Loads the permalink without the bearer token, like a logged-out visitor would.
+pub async fn is_publicly_visible(client: &AuthRedditClient, permalink: &str) -> Result<bool> {
+    let url = format!(
+        "{}{}.json?raw_json=1",
+        REDDIT_URL,
+        permalink.trim_end_matches('/')
+    );
+    let (status, body) = client.get_public(url.as_str()).await?;
+    if status == StatusCode::NOT_FOUND || status == StatusCode::FORBIDDEN {
+        return Ok(false);
+    }
+    if !status.is_success() {
+        return Err(Error::Reddit(format!(
+            "public GET {} returned {}",
+            url, status
+        )));
+    }
+    let listings: Vec<Thing<Listing<Thing<PostInfo>>>> = serde_json::from_str(body.as_str())?;
+    let public_post = listings
+        .into_iter()
+        .next()
+        .and_then(|listing| listing.data.children.into_iter().next())
+        .map(|thing| thing.data);
+    Ok(match public_post {
+        Some(public_post) => {
+            public_post.removed_by_category.is_none() && public_post.selftext != "[removed]"
+        }
+        None => false,
+    })
+}
+
+pub async fn removal_report(
+    client: &AuthRedditClient,
+    user: &User,
+) -> Result<Vec<SubredditRemovals>> {
+    let posts = post::retrieve_all_posts_with(client, user, |_| true).await?;
+    let mut report: BTreeMap<String, SubredditRemovals> = BTreeMap::new();
+    for post in posts {
+        let (state, publicly_visible) = match classify(&post, None) {
+            RemovalState::Live => match is_publicly_visible(client, &post.permalink).await {
+                Ok(visible) => (classify(&post, Some(visible)), Some(visible)),
+                Err(check_error) => {
+                    warn!("Can't check if post {} is public: {}", post.id, check_error);
+                    (RemovalState::Unknown, None)
+                }
+            },
+            state => (state, None),
+        };
+        info!("Post {} in {} is {:?}", post.id, post.subreddit, state);
+
+        let key = post.subreddit.to_lowercase();
+        let subreddit = report.entry(key).or_insert_with(|| SubredditRemovals {
+            subreddit: post.subreddit.clone(),
+            ..SubredditRemovals::default()
+        });
+        match state {
+            RemovalState::Live => subreddit.live += 1,
+            RemovalState::ModRemoved => subreddit.mod_removed += 1,
+            RemovalState::SpamFiltered => subreddit.spam_filtered += 1,
+            RemovalState::Deleted => subreddit.deleted += 1,
+            RemovalState::Unknown => subreddit.unknown += 1,
+        }
+        subreddit.posts.push(PostRemoval {
+            id: post.id,
+            subreddit: post.subreddit,
+            title: post.title,
+            permalink: post.permalink,
+            state,
+            removed_by_category: post.removed_by_category,
+            publicly_visible,
+        });
+    }
+    Ok(report.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::post_info;
+    use chrono::Utc;
+
+    fn removed(category: &str) -> PostInfo {
+        PostInfo {
+            removed_by_category: Some(String::from(category)),
+            ..post_info("a", "art", Utc::now(), 1)
+        }
+    }
+
+    #[test]
+    fn posts_are_classified() {
+        let live = post_info("a", "art", Utc::now(), 1);
+        let banned = PostInfo {
+            banned_by: Some(Value::from("AutoModerator")),
+            ..post_info("a", "art", Utc::now(), 1)
+        };
+
+        assert_eq!(classify(&live, Some(true)), RemovalState::Live);
+        assert_eq!(classify(&live, Some(false)), RemovalState::SpamFiltered);
+        assert_eq!(classify(&banned, Some(true)), RemovalState::ModRemoved);
+        assert_eq!(
+            classify(&removed("moderator"), None),
+            RemovalState::ModRemoved
+        );
+        assert_eq!(
+            classify(&removed("reddit"), None),
+            RemovalState::SpamFiltered
+        );
+        assert_eq!(classify(&removed("deleted"), None), RemovalState::Deleted);
+    }
+}
@@ -0,0 +1,275 @@
+use crate::account::Accounts;
+use crate::data_store::DataStore;
+use crate::error::{Error, Result};
+use crate::export::export_history;
+use crate::job::NoProgress;
+use crate::reddit_client::AuthRedditClient;
+use crate::subreddit::SubredditMatcher;
+use crate::{comment, post, user};
+use chrono::{DateTime, Duration, Utc};
+use log::{error, info};
+use serde_derive::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use utoipa::ToSchema;
+
+const POLICY_FILE: &str = ".retention";
+const HISTORY_FILE: &str = "retention_runs.jsonl";
+/// Shorter intervals would query reddit all the time.
+const MIN_INTERVAL_MINUTES: u64 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RetentionPolicy {
+    #[serde(default = "default_interval_minutes")]
+    pub interval_minutes: u64,
+    pub posts: Option<RetentionRule>,
+    pub comments: Option<RetentionRule>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RetentionRule {
+    pub older_than_days: u64,
+    pub score_below: Option<i64>,
+    #[serde(default)]
+    pub except_subreddits: Vec<String>,
+}
+
+impl RetentionRule {
+    pub fn matches(
+        &self,
+        subreddit: &str,
+        score: i64,
+        created_utc: f64,
+        now: DateTime<Utc>,
+    ) -> bool {
+        let threshold = now - Duration::days(self.older_than_days as i64);
+        if created_utc >= threshold.timestamp() as f64 {
+            return false;
+        }
+        if self
+            .score_below
+            .is_some_and(|score_below| score >= score_below)
+        {
+            return false;
+        }
+        !SubredditMatcher::exact(&self.except_subreddits).matches(subreddit)
+    }
+}
+
+fn default_interval_minutes() -> u64 {
+    60
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct RetentionRun {
+    /// Missing in runs recorded before there were several accounts.
+    #[serde(default)]
+    pub account: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub deleted_posts: usize,
+    pub deleted_comments: usize,
+    pub error: Option<String>,
+}
+
+pub struct RetentionRunner {
+    next_run_at: RwLock<Option<DateTime<Utc>>>,
+    history_path: PathBuf,
+}
+
+impl RetentionRunner {
+    pub fn new(data_dir: &str) -> RetentionRunner {
+        RetentionRunner {
+            next_run_at: RwLock::new(None),
+            history_path: PathBuf::from(data_dir).join(HISTORY_FILE),
+        }
+    }
+
+    pub fn next_run_at(&self) -> Option<DateTime<Utc>> {
+        *self
+            .next_run_at
+            .read()
+            .expect("can't obtain the lock to retrieve next retention run")
+    }
+
+    fn store_next_run_at(&self, next_run_at: Option<DateTime<Utc>>) {
+        *self
+            .next_run_at
+            .write()
+            .expect("can't obtain the lock to store next retention run") = next_run_at;
+    }
+
+    pub fn runs(&self) -> Result<Vec<RetentionRun>> {
+        let content = match fs::read_to_string(&self.history_path) {
+            Ok(content) => content,
+            Err(_) => return Ok(Vec::new()),
+        };
+        let mut runs = Vec::new();
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            runs.push(serde_json::from_str(line)?);
+        }
+        Ok(runs)
+    }
+
+    fn record(&self, run: &RetentionRun) -> Result<()> {
+        if let Some(parent) = self.history_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.history_path)?;
+        serde_json::to_writer(&mut file, run)?;
+        file.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Evaluates the `.retention` policy forever, re-reading the file before every run.
+    pub async fn run_periodically(
+        &self,
+        data_store: &impl DataStore,
+        accounts: &Accounts,
+        export_dir: &str,
+    ) {
+        loop {
+            let interval = read_policy()
+                .map(|policy| policy.interval_minutes)
+                .unwrap_or_else(default_interval_minutes)
+                .max(MIN_INTERVAL_MINUTES);
+            let next_run_at = Utc::now() + Duration::minutes(interval as i64);
+            self.store_next_run_at(Some(next_run_at));
+            info!("Next retention run at {}", next_run_at);
+            actix_web::rt::time::sleep(std::time::Duration::from_secs(interval * 60)).await;
+
+            match read_policy() {
+                Some(policy) => {
+                    let names = data_store.accounts();
+                    if names.is_empty() {
+                        info!("Not logged in, retention run skipped");
+                    }
+                    for name in names {
+                        self.run_once(&policy, data_store, accounts, &name, export_dir)
+                            .await
+                    }
+                }
+                None => info!("No retention policy found, nothing to do"),
+            }
+        }
+    }
+
+    async fn run_once(
+        &self,
+        policy: &RetentionPolicy,
+        data_store: &impl DataStore,
+        accounts: &Accounts,
+        name: &str,
+        export_dir: &str,
+    ) {
+        let started_at = Utc::now();
+        let result = match (accounts.get(name), data_store.find_auth_token(name)) {
+            (Ok(account), Some(auth_token)) => {
+                let client = account.client(auth_token);
+                apply(&client, policy, export_dir, started_at).await
+            }
+            (Err(error), _) => Err(error),
+            (_, None) => Err(Error::Reddit(String::from(
+                "not logged in, retention run skipped",
+            ))),
+        };
+        let run = match result {
+            Ok((deleted_posts, deleted_comments)) => RetentionRun {
+                account: Some(name.to_string()),
+                started_at,
+                finished_at: Utc::now(),
+                deleted_posts,
+                deleted_comments,
+                error: None,
+            },
+            Err(error) => RetentionRun {
+                account: Some(name.to_string()),
+                started_at,
+                finished_at: Utc::now(),
+                deleted_posts: 0,
+                deleted_comments: 0,
+                error: Some(error.to_string()),
+            },
+        };
+        info!("Retention run finished {:?}", run);
+        if let Err(error) = self.record(&run) {
+            error!("Can't record retention run: {}", error);
+        }
+    }
+}
+
+pub fn read_policy() -> Option<RetentionPolicy> {
+    let content = fs::read_to_string(format!("server/{}", POLICY_FILE))
+        .or_else(|_| fs::read_to_string(POLICY_FILE))
+        .ok()?;
+    match serde_json::from_str(&content) {
+        Ok(policy) => Some(policy),
+        Err(parse_error) => {
+            error!("Retention policy is not well-formatted: {}", parse_error);
+            None
+        }
+    }
+}
+
+/// Deletes everything the policy matches and returns the number of deleted posts and comments.
+pub async fn apply(
+    client: &AuthRedditClient,
+    policy: &RetentionPolicy,
+    export_dir: &str,
+    now: DateTime<Utc>,
+) -> Result<(usize, usize)> {
+    let user = user::me(client).await?;
+    export_history(client, &user, export_dir).await?;
+
+    let posts = match &policy.posts {
+        Some(rule) => {
+            post::retrieve_all_posts_with(client, &user, |post| {
+                rule.matches(&post.subreddit, post.score, post.created_utc, now)
+            })
+            .await?
+        }
+        None => Vec::new(),
+    };
+    post::delete_posts(client, &posts, &NoProgress).await;
+
+    let comments = match &policy.comments {
+        Some(rule) => {
+            comment::retrieve_all_with(client, &user, |comment| {
+                rule.matches(&comment.subreddit, comment.score, comment.created_utc, now)
+            })
+            .await?
+        }
+        None => Vec::new(),
+    };
+    comment::delete_comments(client, &comments, &NoProgress).await;
+
+    Ok((posts.len(), comments.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn rule_matches_old_low_scored_items_outside_excepted_subreddits() {
+        let now = Utc.with_ymd_and_hms(2023, 6, 1, 0, 0, 0).unwrap();
+        let old = (now - Duration::days(100)).timestamp() as f64;
+        let recent = (now - Duration::days(10)).timestamp() as f64;
+        let rule = RetentionRule {
+            older_than_days: 90,
+            score_below: Some(10),
+            except_subreddits: vec![String::from("r/Keep")],
+        };
+
+        assert!(rule.matches("art", 3, old, now));
+        assert!(!rule.matches("art", 3, recent, now));
+        assert!(!rule.matches("art", 10, old, now));
+        assert!(!rule.matches("keep", 3, old, now));
+    }
+}
@@ -0,0 +1,451 @@
+use crate::cooldown::{self, CooldownPolicy};
+use crate::duplicate::DuplicateSettings;
+use crate::error::Result;
+use crate::history::{History, Submission};
+use crate::json_file;
+use crate::post::{self, FinalPost, Recurrence, SubmissionStatus};
+use crate::reddit_client::AuthRedditClient;
+use chrono::{DateTime, Duration, Utc};
+use log::{error, info};
+use serde_derive::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::RwLock;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+pub const SCHEDULE_FILE: &str = "schedule.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ScheduleStatus {
+    Pending,
+    Done,
+    Failed,
+    Cancelled,
+    Skipped,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ScheduledPost {
+    pub id: String,
+    pub post: FinalPost,
+    pub post_at: DateTime<Utc>,
+    pub recurrence: Option<Recurrence>,
+    pub status: ScheduleStatus,
+    #[serde(default)]
+    pub posted_ids: Vec<String>,
+    #[serde(default)]
+    pub duplicate_check: Option<DuplicateSettings>,
+    /// Overrides the cooldown of the subreddit from `.cooldowns`.
+    #[serde(default)]
+    pub cooldown_days: Option<u32>,
+}
+
+impl ScheduledPost {
+    /// Moves `post_at` to the first recurrence after `now`, `false` when there is none left.
+    /// Occurrences missed while the server was down are not caught up on.
+    fn next_occurrence(&mut self, now: DateTime<Utc>) -> bool {
+        match &self.recurrence {
+            Some(recurrence)
+                if recurrence.check().is_ok()
+                    && recurrence
+                        .times
+                        .is_none_or(|times| self.posted_ids.len() < times as usize) =>
+            {
+                let every = Duration::hours(recurrence.every_hours as i64);
+                let missed = (now - self.post_at).num_seconds().max(0) / every.num_seconds();
+                self.post_at += every * (missed as i32 + 1);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Reason to skip the post when it's due, the cooldown is checked again since other posts
+    /// may have been submitted after it was scheduled. Earlier occurrences don't count.
+    fn cooldown(
+        &self,
+        submissions: &[Submission],
+        cooldowns: &CooldownPolicy,
+        now: DateTime<Utc>,
+    ) -> Option<String> {
+        let days = self
+            .cooldown_days
+            .or_else(|| cooldowns.days_for(&self.post.subreddit))?;
+        let others: Vec<Submission> = submissions
+            .iter()
+            .filter(|submission| !self.posted_ids.contains(&submission.id))
+            .cloned()
+            .collect();
+        cooldown::check(&others, &self.post.subreddit, &self.post.url, days, now)
+    }
+}
+
+/// Queue of posts waiting for their time, persisted to `schedule.json` on every change.
+/// The file is read on every access because the CLI and the server both write it.
+pub struct Schedule {
+    path: PathBuf,
+    lock: RwLock<()>,
+}
+
+impl Schedule {
+    pub fn load(data_dir: &str) -> Result<Schedule> {
+        let path = PathBuf::from(data_dir).join(SCHEDULE_FILE);
+        json_file::read::<Vec<ScheduledPost>>(&path)?;
+        Ok(Schedule {
+            path,
+            lock: RwLock::new(()),
+        })
+    }
+
+    pub fn list(&self) -> Vec<ScheduledPost> {
+        let _guard = self
+            .lock
+            .read()
+            .expect("can't obtain the lock to read schedule");
+        json_file::read(&self.path).unwrap_or_else(|read_error| {
+            error!("Schedule file is not well-formatted: {}", read_error);
+            Vec::new()
+        })
+    }
+
+    pub fn add(
+        &self,
+        post: FinalPost,
+        post_at: DateTime<Utc>,
+        recurrence: Option<Recurrence>,
+        duplicate_check: Option<DuplicateSettings>,
+        cooldown_days: Option<u32>,
+    ) -> Result<ScheduledPost> {
+        if let Some(recurrence) = &recurrence {
+            recurrence.check()?;
+        }
+        let scheduled = ScheduledPost {
+            id: Uuid::new_v4().to_string(),
+            post,
+            post_at,
+            recurrence,
+            status: ScheduleStatus::Pending,
+            posted_ids: Vec::new(),
+            duplicate_check,
+            cooldown_days,
+        };
+        self.update(|entries| {
+            entries.push(scheduled.clone());
+            Some(scheduled)
+        })
+        .map(|scheduled| scheduled.expect("just added"))
+    }
+
+    /// Moves a pending post, refused for recurrences stored before they were checked.
+    pub fn reschedule(&self, id: &str, post_at: DateTime<Utc>) -> Result<Option<ScheduledPost>> {
+        let pending = self
+            .list()
+            .into_iter()
+            .find(|entry| entry.id == id && entry.status == ScheduleStatus::Pending);
+        if let Some(recurrence) = pending.and_then(|entry| entry.recurrence) {
+            recurrence.check()?;
+        }
+        self.update(|entries| {
+            let entry = entries
+                .iter_mut()
+                .find(|entry| entry.id == id && entry.status == ScheduleStatus::Pending)?;
+            entry.post_at = post_at;
+            Some(entry.clone())
+        })
+    }
+
+    pub fn cancel(&self, id: &str) -> Result<Option<ScheduledPost>> {
+        self.update(|entries| {
+            let entry = entries
+                .iter_mut()
+                .find(|entry| entry.id == id && entry.status == ScheduleStatus::Pending)?;
+            entry.status = ScheduleStatus::Cancelled;
+            Some(entry.clone())
+        })
+    }
+
+    pub fn due(&self, now: DateTime<Utc>) -> Vec<ScheduledPost> {
+        self.list()
+            .into_iter()
+            .filter(|entry| entry.status == ScheduleStatus::Pending && entry.post_at <= now)
+            .collect()
+    }
+
+    /// Stores the submission result and moves recurring posts to their next time.
+    pub fn complete(
+        &self,
+        id: &str,
+        posted_id: Option<String>,
+        now: DateTime<Utc>,
+    ) -> Result<Option<ScheduledPost>> {
+        self.update(|entries| {
+            let entry = entries.iter_mut().find(|entry| entry.id == id)?;
+            let posted_id = match posted_id {
+                Some(posted_id) => posted_id,
+                None => {
+                    entry.status = ScheduleStatus::Failed;
+                    return Some(entry.clone());
+                }
+            };
+            entry.posted_ids.push(posted_id);
+            if !entry.next_occurrence(now) {
+                entry.status = ScheduleStatus::Done;
+            }
+            Some(entry.clone())
+        })
+    }
+
+    /// Drops a duplicate occurrence or one inside a cooldown, recurring posts still move to their next time.
+    pub fn skip(&self, id: &str, now: DateTime<Utc>) -> Result<Option<ScheduledPost>> {
+        self.update(|entries| {
+            let entry = entries.iter_mut().find(|entry| entry.id == id)?;
+            if !entry.next_occurrence(now) {
+                entry.status = ScheduleStatus::Skipped;
+            }
+            Some(entry.clone())
+        })
+    }
+
+    /// Changes the latest content of the file, a malformed file is left as it is.
+    fn update<T>(
+        &self,
+        change: impl FnOnce(&mut Vec<ScheduledPost>) -> Option<T>,
+    ) -> Result<Option<T>> {
+        let _guard = self
+            .lock
+            .write()
+            .expect("can't obtain the lock to update schedule");
+        let mut entries = json_file::read(&self.path)?;
+        let result = change(&mut entries);
+        if result.is_some() {
+            json_file::write(&self.path, &entries)?;
+        }
+        Ok(result)
+    }
+
+    /// Submits the posts whose time has come.
+    pub async fn publish_due(&self, client: &AuthRedditClient, history: &History) {
+        let now = Utc::now();
+        let cooldowns = cooldown::read_policy();
+        for scheduled in self.due(now) {
+            info!("Posting scheduled {:?}", scheduled);
+            let duplicate_check = scheduled.duplicate_check.clone().unwrap_or_default();
+            let status = match scheduled.cooldown(&history.list(), &cooldowns, now) {
+                Some(reason) => SubmissionStatus::Skipped { reason },
+                None => {
+                    // earlier occurrences of a recurring post are in the history, they aren't
+                    // duplicates
+                    let own_ids = &scheduled.posted_ids;
+                    post::publish(client, &scheduled.post, history, &duplicate_check, own_ids)
+                        .await
+                        .map(|result| result.status)
+                        .unwrap_or_else(|error| {
+                            error!("Can't publish scheduled post {}: {}", scheduled.id, error);
+                            SubmissionStatus::Failed
+                        })
+                }
+            };
+            let result = match status {
+                SubmissionStatus::Posted { id } => self.complete(&scheduled.id, Some(id), now),
+                SubmissionStatus::Skipped { reason } => {
+                    info!("Skipped scheduled post {}: {}", scheduled.id, reason);
+                    self.skip(&scheduled.id, now)
+                }
+                _ => self.complete(&scheduled.id, None, now),
+            };
+            if let Err(error) = result {
+                error!("Can't store scheduled post {}: {}", scheduled.id, error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::duplicate;
+    use crate::test_support;
+    use chrono::TimeZone;
+
+    fn final_post() -> FinalPost {
+        test_support::final_post("rust", "https://example.com")
+    }
+
+    #[test]
+    fn recurring_posts_are_rescheduled_until_done_and_survive_reload() {
+        let dir = std::env::temp_dir().join(format!("schedule-test-{}", Uuid::new_v4()));
+        let data_dir = dir.to_str().unwrap();
+        let now = Utc.with_ymd_and_hms(2023, 6, 5, 8, 0, 0).unwrap();
+        let schedule = Schedule::load(data_dir).unwrap();
+        let recurrence = Recurrence {
+            every_hours: 24,
+            times: Some(2),
+        };
+        let scheduled = schedule
+            .add(final_post(), now, Some(recurrence), None, None)
+            .unwrap();
+        let cancelled = schedule.add(final_post(), now, None, None, None).unwrap();
+        schedule.cancel(&cancelled.id).unwrap();
+
+        assert_eq!(schedule.due(now).len(), 1);
+        let first = schedule
+            .complete(&scheduled.id, Some(String::from("p1")), now)
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.post_at, now + Duration::hours(24));
+        assert_eq!(first.status, ScheduleStatus::Pending);
+        assert!(schedule.due(now).is_empty());
+
+        let reloaded = Schedule::load(data_dir).unwrap();
+        let second = reloaded
+            .complete(&scheduled.id, Some(String::from("p2")), now)
+            .unwrap()
+            .unwrap();
+        assert_eq!(second.status, ScheduleStatus::Done);
+        assert_eq!(second.posted_ids, vec!["p1", "p2"]);
+        assert_eq!(reloaded.list()[1].status, ScheduleStatus::Cancelled);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missed_occurrences_are_not_caught_up_and_zero_hours_are_refused() {
+        let dir = std::env::temp_dir().join(format!("schedule-test-{}", Uuid::new_v4()));
+        let data_dir = dir.to_str().unwrap();
+        let start = Utc.with_ymd_and_hms(2023, 6, 5, 8, 0, 0).unwrap();
+        let schedule = Schedule::load(data_dir).unwrap();
+        let daily = Recurrence {
+            every_hours: 24,
+            times: None,
+        };
+        let scheduled = schedule
+            .add(final_post(), start, Some(daily), None, None)
+            .unwrap();
+
+        let after_downtime = start + Duration::days(3) + Duration::hours(2);
+        let next = schedule
+            .complete(&scheduled.id, Some(String::from("p1")), after_downtime)
+            .unwrap()
+            .unwrap();
+        assert_eq!(next.post_at, start + Duration::days(4));
+        assert!(schedule.due(after_downtime).is_empty());
+
+        let zero = Recurrence {
+            every_hours: 0,
+            times: None,
+        };
+        assert!(schedule
+            .add(final_post(), start, Some(zero), None, None)
+            .is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn earlier_occurrences_are_not_duplicates_of_the_next_one() {
+        let dir = std::env::temp_dir().join(format!("schedule-test-{}", Uuid::new_v4()));
+        let data_dir = dir.to_str().unwrap();
+        let now = Utc.with_ymd_and_hms(2023, 6, 5, 8, 0, 0).unwrap();
+        let schedule = Schedule::load(data_dir).unwrap();
+        let history = History::load(data_dir).unwrap();
+        let daily = Recurrence {
+            every_hours: 24,
+            times: Some(2),
+        };
+        let scheduled = schedule
+            .add(final_post(), now, Some(daily), None, None)
+            .unwrap();
+        let since = now - Duration::days(DuplicateSettings::default().window_days as i64);
+
+        let mut published = Vec::new();
+        for day in 0..2 {
+            let post_at = now + Duration::days(day);
+            let entry = schedule.due(post_at).pop().unwrap();
+            let submissions = history.list();
+            let duplicate =
+                duplicate::find_in_history(&submissions, &entry.post, since, &entry.posted_ids);
+            assert_eq!(duplicate, None, "occurrence {}", day);
+            let id = format!("p{}", day);
+            history
+                .record(Submission::new(id.clone(), &entry.post, post_at))
+                .unwrap();
+            published.push(schedule.complete(&entry.id, Some(id), post_at).unwrap());
+        }
+
+        let last = published.pop().unwrap().unwrap();
+        assert_eq!(last.id, scheduled.id);
+        assert_eq!(last.status, ScheduleStatus::Done);
+        assert_eq!(last.posted_ids, vec!["p0", "p1"]);
+        let other = schedule.add(final_post(), now, None, None, None).unwrap();
+        let submissions = history.list();
+        let duplicate = duplicate::find_in_history(&submissions, &other.post, since, &[]);
+        assert_eq!(
+            duplicate.map(|duplicate| duplicate.id),
+            Some(String::from("p1"))
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn the_cooldown_is_checked_again_when_the_post_is_due() {
+        let dir = std::env::temp_dir().join(format!("schedule-test-{}", Uuid::new_v4()));
+        let data_dir = dir.to_str().unwrap();
+        let now = Utc.with_ymd_and_hms(2023, 6, 5, 8, 0, 0).unwrap();
+        let schedule = Schedule::load(data_dir).unwrap();
+        let daily = Recurrence {
+            every_hours: 24,
+            times: None,
+        };
+        let recurring = schedule
+            .add(final_post(), now, Some(daily), None, Some(7))
+            .unwrap();
+        let once = schedule.add(final_post(), now, None, None, None).unwrap();
+        let recurring = schedule
+            .complete(&recurring.id, Some(String::from("p1")), now)
+            .unwrap()
+            .unwrap();
+        let mut submission = Submission::new(String::from("p1"), &recurring.post, now);
+        submission.url = String::from("https://example.com/");
+        let submissions = vec![submission];
+        let cooldowns = CooldownPolicy {
+            default_days: Some(3),
+            ..CooldownPolicy::default()
+        };
+
+        let reason = once.cooldown(&submissions, &cooldowns, now + Duration::days(1));
+        assert!(reason.unwrap().contains("3 days cooldown"));
+        assert_eq!(
+            once.cooldown(&submissions, &cooldowns, now + Duration::days(3)),
+            None
+        );
+        let next_day = recurring.post_at;
+        assert_eq!(recurring.cooldown(&submissions, &cooldowns, next_day), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn changes_of_another_process_are_not_lost() {
+        let dir = std::env::temp_dir().join(format!("schedule-test-{}", Uuid::new_v4()));
+        let data_dir = dir.to_str().unwrap();
+        let now = Utc.with_ymd_and_hms(2023, 6, 5, 8, 0, 0).unwrap();
+        let server = Schedule::load(data_dir).unwrap();
+        let cli = Schedule::load(data_dir).unwrap();
+
+        let first = server.add(final_post(), now, None, None, None).unwrap();
+        let second = cli.add(final_post(), now, None, None, None).unwrap();
+        server.cancel(&second.id).unwrap();
+
+        let statuses: Vec<(String, ScheduleStatus)> = cli
+            .list()
+            .into_iter()
+            .map(|entry| (entry.id, entry.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (first.id, ScheduleStatus::Pending),
+                (second.id, ScheduleStatus::Cancelled)
+            ]
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
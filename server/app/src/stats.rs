@@ -0,0 +1,64 @@
+use serde_derive::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct Distribution {
+    pub count: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub p25: f64,
+    pub p75: f64,
+    pub p90: f64,
+    pub max: f64,
+}
+
+impl Distribution {
+    pub fn of(values: impl IntoIterator<Item = f64>) -> Distribution {
+        let mut values: Vec<f64> = values.into_iter().collect();
+        values.sort_by(f64::total_cmp);
+        let count = values.len();
+        let mean = if count == 0 {
+            0.0
+        } else {
+            values.iter().sum::<f64>() / count as f64
+        };
+        Distribution {
+            count,
+            mean,
+            median: percentile(&values, 50.0),
+            p25: percentile(&values, 25.0),
+            p75: percentile(&values, 75.0),
+            p90: percentile(&values, 90.0),
+            max: values.last().copied().unwrap_or(0.0),
+        }
+    }
+}
+
+/// Linear interpolated percentile of already sorted values, `0.0` when there are none.
+pub fn percentile(sorted_values: &[f64], percent: f64) -> f64 {
+    if sorted_values.is_empty() {
+        return 0.0;
+    }
+    let rank = percent / 100.0 * (sorted_values.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    let weight = rank - lower as f64;
+    sorted_values[lower] + (sorted_values[upper] - sorted_values[lower]) * weight
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distribution_interpolates_percentiles() {
+        let distribution = Distribution::of(vec![4.0, 1.0, 3.0, 2.0]);
+
+        assert_eq!(distribution.count, 4);
+        assert_eq!(distribution.mean, 2.5);
+        assert_eq!(distribution.median, 2.5);
+        assert_eq!(distribution.p25, 1.75);
+        assert_eq!(distribution.max, 4.0);
+        assert_eq!(Distribution::of(Vec::new()).median, 0.0);
+    }
+}
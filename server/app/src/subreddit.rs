@@ -1,74 +1,367 @@
-use crate::reddit_client::{AuthRedditClient, DeleteRequest};
+use crate::comment::Comment;
+use crate::error::{Error, Result};
+use crate::job::Progress;
+use crate::post::PostInfo;
+use crate::reddit_client::AuthRedditClient;
 use crate::user::User;
 use crate::{comment, post};
+use chrono::{DateTime, Utc};
+use glob::{MatchOptions, Pattern};
 use log::info;
+use regex::{Regex, RegexBuilder};
+use serde_derive::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
-pub async fn get_all_from(client: &AuthRedditClient, user: &User, sub_reddit: String) {
-    let posts = post::retrieve_all_posts_with(client, user, |post| {
-        post.subreddit
-            .to_lowercase()
-            .contains(&sub_reddit.to_lowercase())
-    })
-    .await;
-    info!("Found {} posts", posts.len());
-    for post in posts {
-        info!("Found {:?} post", post);
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    #[default]
+    Exact,
+    Glob,
+    Regex,
+}
+
+/// Decides which subreddits are affected, names are compared case-insensitively and patterns have
+/// to match the whole name.
+#[derive(Debug)]
+pub enum SubredditMatcher {
+    Exact(Vec<String>),
+    Glob(Vec<Pattern>),
+    Regex(Vec<Regex>),
+}
+
+impl SubredditMatcher {
+    /// Refuses empty names, an empty pattern would match every subreddit.
+    pub fn new(sub_reddits: &[String], mode: MatchMode) -> Result<SubredditMatcher> {
+        if sub_reddits.is_empty() || sub_reddits.iter().any(|name| normalize(name).is_empty()) {
+            return Err(Error::InvalidPattern(String::from(
+                "subreddit names or patterns must not be empty",
+            )));
+        }
+        let sub_reddits = sub_reddits.iter().map(|sub_reddit| normalize(sub_reddit));
+        let matcher = match mode {
+            MatchMode::Exact => SubredditMatcher::Exact(sub_reddits.map(String::from).collect()),
+            MatchMode::Glob => SubredditMatcher::Glob(
+                sub_reddits
+                    .map(|pattern| {
+                        Pattern::new(pattern).map_err(|error| {
+                            Error::InvalidPattern(format!("{}: {}", pattern, error))
+                        })
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            MatchMode::Regex => SubredditMatcher::Regex(
+                sub_reddits
+                    .map(|pattern| {
+                        RegexBuilder::new(&format!("^(?:{})$", pattern))
+                            .case_insensitive(true)
+                            .build()
+                            .map_err(|error| {
+                                Error::InvalidPattern(format!("{}: {}", pattern, error))
+                            })
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+        };
+        Ok(matcher)
     }
 
-    let comments = comment::retrieve_all_with(client, user, |comment| {
-        comment
-            .subreddit
-            .to_lowercase()
-            .contains(&sub_reddit.to_lowercase())
-    })
-    .await;
-    info!("Found {} comments", comments.len());
-    for comment in comments {
-        info!("Found {:?} comment", comment);
+    pub fn exact(sub_reddits: &[String]) -> SubredditMatcher {
+        SubredditMatcher::Exact(
+            sub_reddits
+                .iter()
+                .map(|sub_reddit| String::from(normalize(sub_reddit)))
+                .collect(),
+        )
+    }
+
+    pub fn matches(&self, sub_reddit: &str) -> bool {
+        match self {
+            SubredditMatcher::Exact(names) => names
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(sub_reddit)),
+            SubredditMatcher::Glob(patterns) => {
+                let options = MatchOptions {
+                    case_sensitive: false,
+                    ..MatchOptions::new()
+                };
+                patterns
+                    .iter()
+                    .any(|pattern| pattern.matches_with(sub_reddit, options))
+            }
+            SubredditMatcher::Regex(regexes) => {
+                regexes.iter().any(|regex| regex.is_match(sub_reddit))
+            }
+        }
     }
 }
 
-pub async fn delete_all_from(client: &AuthRedditClient, user: &User, sub_reddit: String) {
-    let posts = post::retrieve_all_posts_with(client, user, |post| {
-        post.subreddit
-            .to_lowercase()
-            .contains(&sub_reddit.to_lowercase())
-    })
-    .await;
-    info!("Found {} posts", posts.len());
-    for post in posts {
-        let delete_request = DeleteRequest::new_json(post.name.as_str());
-        info!("Will delete post {:?}", post);
-        client.delete(&delete_request).await;
+pub struct SubredditActivity {
+    pub posts: Vec<PostInfo>,
+    pub comments: Vec<Comment>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    #[default]
+    New,
+    Old,
+    Top,
+    Bottom,
+}
+
+#[derive(Deserialize, Debug, IntoParams)]
+pub struct PageRequest {
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    #[serde(default)]
+    #[param(inline)]
+    pub sort: SortBy,
+}
+
+fn default_limit() -> usize {
+    100
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ActivitySummary {
+    pub count: usize,
+    pub total_score: i64,
+    pub average_score: f64,
+    pub first_activity: Option<DateTime<Utc>>,
+    pub last_activity: Option<DateTime<Utc>>,
+}
+
+impl ActivitySummary {
+    fn of(scores_and_times: &[(i64, f64)]) -> ActivitySummary {
+        let count = scores_and_times.len();
+        let total_score = scores_and_times.iter().map(|(score, _)| score).sum();
+        let average_score = if count == 0 {
+            0.0
+        } else {
+            total_score as f64 / count as f64
+        };
+        let times = scores_and_times.iter().map(|(_, created_utc)| *created_utc);
+        ActivitySummary {
+            count,
+            total_score,
+            average_score,
+            first_activity: times.clone().reduce(f64::min).and_then(to_date_time),
+            last_activity: times.reduce(f64::max).and_then(to_date_time),
+        }
     }
+}
 
-    let comments = comment::retrieve_all_with(client, user, |comment| {
-        comment
-            .subreddit
-            .to_lowercase()
-            .contains(&sub_reddit.to_lowercase())
-    })
-    .await;
-    info!("Found {} comments", comments.len());
-    for comment in comments {
-        let delete_request = DeleteRequest::new_json(comment.name.as_str());
-        info!("Will delete comment {:?}", comment);
-        client.delete(&delete_request).await;
+#[derive(Serialize, Debug, ToSchema)]
+pub struct Page<T> {
+    pub summary: ActivitySummary,
+    pub offset: usize,
+    pub limit: usize,
+    pub items: Vec<T>,
+}
+
+impl<T> Page<T> {
+    fn of(
+        mut items: Vec<T>,
+        page: &PageRequest,
+        score_and_time: impl Fn(&T) -> (i64, f64),
+    ) -> Page<T> {
+        let scores_and_times: Vec<(i64, f64)> = items.iter().map(&score_and_time).collect();
+        let summary = ActivitySummary::of(&scores_and_times);
+        match page.sort {
+            SortBy::New => {
+                items.sort_by(|a, b| score_and_time(b).1.total_cmp(&score_and_time(a).1))
+            }
+            SortBy::Old => {
+                items.sort_by(|a, b| score_and_time(a).1.total_cmp(&score_and_time(b).1))
+            }
+            SortBy::Top => items.sort_by_key(|item| std::cmp::Reverse(score_and_time(item).0)),
+            SortBy::Bottom => items.sort_by_key(|item| score_and_time(item).0),
+        }
+        Page {
+            summary,
+            offset: page.offset,
+            limit: page.limit,
+            items: items
+                .into_iter()
+                .skip(page.offset)
+                .take(page.limit)
+                .collect(),
+        }
     }
 }
 
-pub async fn delete_comments_from(client: &AuthRedditClient, user: &User, sub_reddit: String) {
-    let comments = comment::retrieve_all_with(client, user, |comment| {
-        comment
-            .subreddit
-            .to_lowercase()
-            .contains(&sub_reddit.to_lowercase())
-    })
-    .await;
+#[derive(Serialize, Debug, ToSchema)]
+pub struct SubredditReport {
+    pub posts: Page<PostInfo>,
+    pub comments: Page<Comment>,
+}
+
+impl SubredditReport {
+    pub fn new(activity: SubredditActivity, page: &PageRequest) -> SubredditReport {
+        SubredditReport {
+            posts: Page::of(activity.posts, page, |post| (post.score, post.created_utc)),
+            comments: Page::of(activity.comments, page, |comment| {
+                (comment.score, comment.created_utc)
+            }),
+        }
+    }
+}
+
+fn to_date_time(created_utc: f64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(created_utc as i64, 0)
+}
+
+fn normalize(sub_reddit: &str) -> &str {
+    let sub_reddit = sub_reddit.trim();
+    sub_reddit
+        .strip_prefix("/r/")
+        .or_else(|| sub_reddit.strip_prefix("r/"))
+        .unwrap_or(sub_reddit)
+}
+
+pub async fn get_all_from(
+    client: &AuthRedditClient,
+    user: &User,
+    matcher: &SubredditMatcher,
+) -> Result<SubredditActivity> {
+    let posts =
+        post::retrieve_all_posts_with(client, user, |post| matcher.matches(&post.subreddit))
+            .await?;
+    info!("Found {} posts", posts.len());
+
+    let comments =
+        comment::retrieve_all_with(client, user, |comment| matcher.matches(&comment.subreddit))
+            .await?;
+    info!("Found {} comments", comments.len());
+    Ok(SubredditActivity { posts, comments })
+}
+
+pub async fn delete_all_from(
+    client: &AuthRedditClient,
+    user: &User,
+    matcher: &SubredditMatcher,
+    progress: &impl Progress,
+) -> Result<()> {
+    let posts =
+        post::retrieve_all_posts_with(client, user, |post| matcher.matches(&post.subreddit))
+            .await?;
+    info!("Found {} posts", posts.len());
+    post::delete_posts(client, &posts, progress).await;
+
+    delete_comments_from(client, user, matcher, progress).await
+}
+
+pub async fn delete_comments_from(
+    client: &AuthRedditClient,
+    user: &User,
+    matcher: &SubredditMatcher,
+    progress: &impl Progress,
+) -> Result<()> {
+    let comments =
+        comment::retrieve_all_with(client, user, |comment| matcher.matches(&comment.subreddit))
+            .await?;
     info!("Found {} comments", comments.len());
-    for comment in comments {
-        let delete_request = DeleteRequest::new_json(comment.name.as_str());
-        info!("Will delete comment {:?}", comment);
-        client.delete(&delete_request).await;
+    comment::delete_comments(client, &comments, progress).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| String::from(*name)).collect()
+    }
+
+    #[test]
+    fn exact_mode_does_not_match_substrings() {
+        let matcher = SubredditMatcher::new(&names(&["r/Art", "pics"]), MatchMode::Exact).unwrap();
+
+        assert!(matcher.matches("art"));
+        assert!(matcher.matches("Pics"));
+        assert!(!matcher.matches("youareart"));
+        assert!(!matcher.matches("pixelart"));
+    }
+
+    #[test]
+    fn report_summarizes_everything_but_returns_one_page() {
+        let comment = |name: &str, score: i64, created_utc: f64| Comment {
+            id: String::from(name),
+            name: format!("t1_{}", name),
+            body: String::new(),
+            upvotes: 0,
+            subreddit: String::from("art"),
+            permalink: String::new(),
+            link_title: None,
+            score,
+            created_utc,
+            author_flair_text: None,
+        };
+        let activity = SubredditActivity {
+            posts: Vec::new(),
+            comments: vec![
+                comment("a", 1, 1000.0),
+                comment("b", 7, 3000.0),
+                comment("c", 4, 2000.0),
+            ],
+        };
+        let page = PageRequest {
+            offset: 0,
+            limit: 2,
+            sort: SortBy::Top,
+        };
+
+        let report = SubredditReport::new(activity, &page);
+
+        assert_eq!(report.comments.summary.count, 3);
+        assert_eq!(report.comments.summary.total_score, 12);
+        assert_eq!(report.comments.summary.average_score, 4.0);
+        assert_eq!(
+            report.comments.summary.first_activity,
+            DateTime::from_timestamp(1000, 0)
+        );
+        assert_eq!(
+            report.comments.summary.last_activity,
+            DateTime::from_timestamp(3000, 0)
+        );
+        let names: Vec<&str> = report
+            .comments
+            .items
+            .iter()
+            .map(|c| c.id.as_str())
+            .collect();
+        assert_eq!(names, vec!["b", "c"]);
+        assert_eq!(report.posts.summary.count, 0);
+        assert_eq!(report.posts.summary.first_activity, None);
+    }
+
+    #[test]
+    fn pattern_modes_are_case_insensitive() {
+        let glob = SubredditMatcher::new(&names(&["*art"]), MatchMode::Glob).unwrap();
+        let regex = SubredditMatcher::new(&names(&["pixel.*"]), MatchMode::Regex).unwrap();
+
+        assert!(glob.matches("PixelArt"));
+        assert!(!glob.matches("artists"));
+        assert!(regex.matches("PixelArt"));
+        assert!(!regex.matches("art"));
+        assert!(SubredditMatcher::new(&names(&["("]), MatchMode::Regex).is_err());
+    }
+
+    #[test]
+    fn patterns_match_whole_names_and_must_not_be_empty() {
+        let regex = SubredditMatcher::new(&names(&["art"]), MatchMode::Regex).unwrap();
+
+        assert!(regex.matches("Art"));
+        assert!(!regex.matches("startups"));
+        assert!(!regex.matches("smartart"));
+        for mode in [MatchMode::Exact, MatchMode::Glob, MatchMode::Regex] {
+            assert!(SubredditMatcher::new(&names(&["art", ""]), mode).is_err());
+            assert!(SubredditMatcher::new(&names(&[" r/ "]), mode).is_err());
+            assert!(SubredditMatcher::new(&[], mode).is_err());
+        }
     }
 }
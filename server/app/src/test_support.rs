@@ -0,0 +1,49 @@
+//! Fixtures shared by the module tests.
+
+use crate::history::Submission;
+use crate::post::{FinalPost, PostInfo};
+use chrono::{DateTime, Utc};
+
+/// Live post `t3_{id}` without flair, comments or votes other than `score`.
+pub fn post_info(id: &str, subreddit: &str, created: DateTime<Utc>, score: i64) -> PostInfo {
+    PostInfo {
+        id: String::from(id),
+        upvotes: score,
+        name: format!("t3_{}", id),
+        subreddit: String::from(subreddit),
+        title: String::from("title"),
+        permalink: String::new(),
+        selftext: String::new(),
+        url: None,
+        score,
+        created_utc: created.timestamp() as f64,
+        link_flair_text: None,
+        num_comments: 0,
+        upvote_ratio: 0.0,
+        removed_by_category: None,
+        banned_by: None,
+    }
+}
+
+/// Link post of `url` to `subreddit`, without flair or comment.
+pub fn final_post(subreddit: &str, url: &str) -> FinalPost {
+    FinalPost {
+        sr: String::from(subreddit),
+        resubmit: false,
+        sendreplies: false,
+        title: String::from("title"),
+        nsfw: false,
+        spoiler: false,
+        subreddit: String::from(subreddit),
+        flair_id: None,
+        flair_name: None,
+        url: String::from(url),
+        kind: String::from("link"),
+        comment: None,
+    }
+}
+
+/// Our submission of `url` to `subreddit`, without snapshots.
+pub fn submission(id: &str, subreddit: &str, url: &str, posted_at: DateTime<Utc>) -> Submission {
+    Submission::new(String::from(id), &final_post(subreddit, url), posted_at)
+}
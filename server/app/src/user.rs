@@ -1,33 +1,86 @@
+use crate::error::Result;
 use crate::reddit_client::AuthRedditClient;
 use crate::OAUTH_REDDIT_URL;
 use log::info;
 use serde_derive::{Deserialize, Serialize};
-use serde_json::Value;
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Profile of the logged in account from `/api/v1/me`, suspended accounts only have the name,
+/// so everything else has a default.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
 pub struct User {
-    id: String,
-    oauth_client_id: String,
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub oauth_client_id: Option<String>,
+    #[serde(default)]
+    pub link_karma: i64,
+    #[serde(default)]
+    pub comment_karma: i64,
+    #[serde(default)]
+    pub created_utc: f64,
+    #[serde(default)]
+    pub is_mod: bool,
+    #[serde(default)]
+    pub has_verified_email: bool,
+    #[serde(default)]
+    pub inbox_count: u64,
+    /// The "I am over eighteen" preference.
+    #[serde(default)]
+    pub over_18: bool,
+    #[serde(default)]
+    pub is_suspended: bool,
+    /// `None` for a permanent suspension.
+    pub suspension_expiration_utc: Option<f64>,
+    pub subreddit: Option<ProfileSubreddit>,
+}
+
+/// Profile page of the account, which is a subreddit named `u_{name}`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+pub struct ProfileSubreddit {
     pub name: String,
     pub display_name: String,
     pub display_name_prefixed: String,
     pub url: String,
+    #[serde(default)]
+    pub over_18: bool,
 }
 
-pub async fn info(client: &AuthRedditClient) -> User {
+impl User {
+    /// Path of the user's listings, `/user/{name}/`.
+    pub fn url(&self) -> String {
+        match &self.subreddit {
+            Some(subreddit) => subreddit.url.clone(),
+            None => format!("/user/{}/", self.name),
+        }
+    }
+}
+
+pub async fn me(client: &AuthRedditClient) -> Result<User> {
     let url = format!("{}/api/v1/me", OAUTH_REDDIT_URL);
+    let user: User = client.get_json(url.as_str()).await?;
+    if user.is_suspended {
+        info!(
+            "Account {} is suspended until {:?}",
+            user.name, user.suspension_expiration_utc
+        );
+    }
+    Ok(user)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let body = client.get(url.as_str()).await;
+    #[test]
+    fn suspended_profile_is_read() {
+        let user: User = serde_json::from_value(serde_json::json!({
+            "name": "someone", "is_suspended": true, "suspension_expiration_utc": null,
+            "awarder_karma": 0
+        }))
+        .unwrap();
 
-    let value: Value = serde_json::from_str(body.as_str()).expect("Json format expected");
-    info!("The json value is {}", value);
-    let subreddit = &value["subreddit"];
-    User {
-        id: String::from(value["id"].as_str().unwrap()),
-        oauth_client_id: String::from(value["oauth_client_id"].as_str().unwrap()),
-        name: String::from(subreddit["name"].as_str().unwrap()),
-        display_name: String::from(subreddit["display_name"].as_str().unwrap()),
-        display_name_prefixed: String::from(subreddit["display_name_prefixed"].as_str().unwrap()),
-        url: String::from(subreddit["url"].as_str().unwrap()),
+        assert!(user.is_suspended);
+        assert_eq!(user.url(), "/user/someone/");
     }
 }
@@ -0,0 +1,373 @@
+"use strict";
+
+// Small dashboard over /api/v1 for the posting workflow: log in, pick subreddits and flairs,
+// preview the final posts and follow the submission job.
+
+const state = {
+    subreddits: [],
+    selected: new Set(),
+    // lowercase subreddit name -> flairs of the last flairs job
+    flairs: new Map(),
+    jobId: null,
+};
+
+const $ = (id) => document.getElementById(id);
+
+function el(tag, attributes = {}, ...children) {
+    const element = document.createElement(tag);
+    for (const [name, value] of Object.entries(attributes)) {
+        if (name === "className") {
+            element.className = value;
+        } else {
+            element.setAttribute(name, value);
+        }
+    }
+    for (const child of children) {
+        element.append(child instanceof Node ? child : String(child ?? ""));
+    }
+    return element;
+}
+
+function table(headers, rows) {
+    return el("table", {},
+        el("thead", {}, el("tr", {}, ...headers.map((header) => el("th", {}, header)))),
+        el("tbody", {}, ...rows.map((cells) => el("tr", {}, ...cells.map((cell) => el("td", {}, cell))))));
+}
+
+function showMessage(text, isError = false) {
+    const message = $("message");
+    message.textContent = text;
+    message.className = isError ? "error" : "";
+}
+
+function headers() {
+    const result = {"Content-Type": "application/json"};
+    const apiKey = sessionStorage.getItem("apiKey");
+    if (apiKey) {
+        result["X-Api-Key"] = apiKey;
+    }
+    const account = $("accounts").value;
+    if (account) {
+        result["X-Reddit-Account"] = account;
+    }
+    return result;
+}
+
+// Calls the API and returns the JSON answer, failures throw with the message of the error envelope.
+async function api(method, path, body) {
+    const response = await fetch(`/api/v1${path}`, {
+        method,
+        headers: headers(),
+        body: body === undefined ? undefined : JSON.stringify(body),
+    });
+    const json = await response.json().catch(() => null);
+    if (!response.ok) {
+        const error = json?.error;
+        const details = error?.details?.length ? `: ${error.details.join("; ")}` : "";
+        throw new Error(`${error?.message ?? response.statusText}${details}`);
+    }
+    return json;
+}
+
+// Follows the events of a job, calls `onChange` with every version and returns the finished job.
+async function followJob(jobId, onChange) {
+    const response = await fetch(`/api/v1/jobs/${jobId}/events`, {headers: headers()});
+    if (!response.ok) {
+        throw new Error(`can't follow job ${jobId}: ${response.statusText}`);
+    }
+    const reader = response.body.pipeThrough(new TextDecoderStream()).getReader();
+    let buffer = "";
+    let job = null;
+    for (;;) {
+        const {value, done} = await reader.read();
+        if (done) {
+            return job;
+        }
+        buffer += value;
+        let end;
+        while ((end = buffer.indexOf("\n\n")) >= 0) {
+            const event = buffer.slice(0, end);
+            buffer = buffer.slice(end + 2);
+            const data = event.split("\n").find((line) => line.startsWith("data: "));
+            if (data) {
+                job = JSON.parse(data.slice("data: ".length));
+                onChange(job);
+            }
+        }
+    }
+}
+
+function renderJob(container, job) {
+    const items = job.items.map((item) => [
+        item.name,
+        el("span", {className: `status-${item.status}`}, item.status),
+        item.detail ?? "",
+    ]);
+    container.replaceChildren(
+        el("p", {}, `${job.kind}: `, el("span", {className: `status-${job.status}`}, job.status),
+            ` (${job.completed} of ${job.total})`),
+        el("progress", {max: Math.max(job.total, 1), value: job.completed}),
+        job.error ? el("p", {className: "error"}, job.error) : "",
+        items.length ? table(["Item", "Status", "Detail"], items) : "",
+    );
+}
+
+// Account
+
+async function loadAccounts(selectName) {
+    const list = await api("GET", "/accounts");
+    const select = $("accounts");
+    const current = selectName ?? select.value ?? sessionStorage.getItem("account");
+    select.replaceChildren(...list.accounts.map((name) => el("option", {value: name}, name)));
+    const preferred = [current, sessionStorage.getItem("account"), list.default_account]
+        .find((name) => name && list.accounts.includes(name));
+    if (preferred) {
+        select.value = preferred;
+    }
+    $("login-status").textContent = list.accounts.length
+        ? `${list.accounts.length} account(s) logged in.`
+        : "No account is logged in yet.";
+    return list.accounts;
+}
+
+async function login() {
+    const answer = await api("POST", "/login");
+    if (answer.status === "logged_in") {
+        await loadAccounts(answer.account);
+        showMessage(`Logged in as ${answer.account}.`);
+        return;
+    }
+    $("login-status").replaceChildren(
+        el("a", {href: answer.url, target: "_blank", rel: "noopener"}, "Open reddit to allow the login"),
+        ", this page notices when it is done.");
+    const before = new Set(Array.from($("accounts").options, (option) => option.value));
+    // login requests expire after 10 minutes
+    for (let attempt = 0; attempt < 300; attempt++) {
+        await new Promise((resolve) => setTimeout(resolve, 2000));
+        const list = await api("GET", "/accounts");
+        const added = list.accounts.find((name) => !before.has(name));
+        if (added) {
+            await loadAccounts(added);
+            showMessage(`Logged in as ${added}.`);
+            return;
+        }
+    }
+    showMessage("The login was not finished in time.", true);
+}
+
+// Subreddits and flairs
+
+function renderSubreddits() {
+    $("subreddit-list").replaceChildren(...state.subreddits.map((name) => {
+        const checkbox = el("input", {type: "checkbox"});
+        checkbox.checked = state.selected.has(name);
+        checkbox.addEventListener("change", () => {
+            if (checkbox.checked) {
+                state.selected.add(name);
+            } else {
+                state.selected.delete(name);
+            }
+            renderTargets();
+        });
+        return el("label", {}, checkbox, ` r/${name}`);
+    }));
+    if (!state.subreddits.length) {
+        $("subreddit-list").textContent = "Add the subreddits to post to.";
+    }
+}
+
+function addSubreddit(event) {
+    event.preventDefault();
+    const name = $("new-subreddit").value.trim().replace(/^\/?r\//, "");
+    if (!name) {
+        return;
+    }
+    if (!state.subreddits.some((known) => known.toLowerCase() === name.toLowerCase())) {
+        state.subreddits.push(name);
+    }
+    state.selected.add(state.subreddits.find((known) => known.toLowerCase() === name.toLowerCase()));
+    $("new-subreddit").value = "";
+    renderSubreddits();
+    renderTargets();
+}
+
+async function loadFlairs() {
+    const subreddits = Array.from(state.selected);
+    if (!subreddits.length) {
+        throw new Error("Pick the subreddits first.");
+    }
+    const started = await api("POST", "/flairs", {subreddits});
+    const job = await followJob(started.job_id, (job) => renderJob($("flairs-progress"), job));
+    if (job?.status !== "done") {
+        throw new Error(`Loading flairs ${job?.status ?? "stopped"}.`);
+    }
+    for (const name of subreddits) {
+        state.flairs.set(name.toLowerCase(), []);
+    }
+    for (const flair of job.result) {
+        state.flairs.get(flair.sub_reddit.toLowerCase())?.push(flair);
+    }
+    renderTargets();
+    showMessage("Flairs loaded.");
+}
+
+// Posts
+
+function renderTargets() {
+    // keeps what was chosen for subreddits which stay selected
+    const chosen = new Map(Array.from($("targets").tBodies[0].rows, (row) => [
+        row.querySelector("select").dataset.subreddit,
+        {flair: row.querySelector("select").value, addition: row.querySelector("input").value},
+    ]));
+    const rows = Array.from(state.selected).map((name) => {
+        const flairs = state.flairs.get(name.toLowerCase());
+        const select = el("select", {"data-subreddit": name},
+            el("option", {value: ""}, flairs ? "no flair" : "load flairs first"),
+            ...(flairs ?? []).map((flair) => el("option", {value: flair.id}, flair.text)));
+        const addition = el("input", {placeholder: "e.g. [OC]"});
+        select.value = chosen.get(name)?.flair ?? "";
+        addition.value = chosen.get(name)?.addition ?? "";
+        return el("tr", {}, el("td", {}, `r/${name}`), el("td", {}, select), el("td", {}, addition));
+    });
+    $("targets").tBodies[0].replaceChildren(...rows);
+    planIsStale();
+}
+
+function posts() {
+    const optional = (value) => value.trim() || null;
+    return {
+        main_post_info: {
+            post_type: "link",
+            body: $("url").value.trim(),
+            title: $("title").value,
+            nsfw: $("nsfw").checked,
+            comment: optional($("comment").value),
+        },
+        posts: Array.from($("targets").tBodies[0].rows, (row) => {
+            const flair = row.querySelector("select");
+            const option = flair.selectedOptions[0];
+            return {
+                subreddit: flair.dataset.subreddit,
+                flair_id: flair.value || null,
+                flair_name: flair.value ? option.textContent : null,
+                additional_title: optional(row.querySelector("input").value),
+            };
+        }),
+    };
+}
+
+function planIsStale() {
+    $("submit").disabled = true;
+}
+
+async function preview() {
+    const plan = await api("POST", "/posts/plan", posts());
+    const planned = plan.planned.map(({post, post_at}) => [
+        `r/${post.subreddit}`,
+        post.title,
+        post.flair_name ?? "",
+        el("a", {href: post.url, target: "_blank", rel: "noopener"}, post.url),
+        post_at ? new Date(post_at).toLocaleString() : "right away",
+    ]);
+    const skipped = plan.skipped.map((result) => [`r/${result.subreddit}`, result.title, result.reason ?? ""]);
+    const ineligible = plan.ineligible.map((entry) => [`r/${entry.subreddit}`, entry.reasons.join("; ")]);
+    $("plan-content").replaceChildren(
+        el("h3", {}, `${planned.length} post(s) will be submitted`),
+        planned.length ? table(["Subreddit", "Title", "Flair", "Link", "When"], planned) : "",
+        skipped.length ? el("h3", {}, "Skipped") : "",
+        skipped.length ? table(["Subreddit", "Title", "Reason"], skipped) : "",
+        ineligible.length ? el("h3", {}, "Not allowed to post") : "",
+        ineligible.length ? table(["Subreddit", "Reasons"], ineligible) : "",
+    );
+    $("submit").disabled = planned.length === 0;
+}
+
+function renderReport(container, report) {
+    const rows = report.map((result) => [
+        `r/${result.subreddit}`,
+        result.title,
+        el("span", {className: `status-${result.status}`}, result.status),
+        result.reason ?? (result.post_at ? new Date(result.post_at).toLocaleString() : result.id ?? ""),
+        result.warnings.join("; "),
+    ]);
+    container.append(el("h3", {}, "Report"), table(["Subreddit", "Title", "Status", "Detail", "Warnings"], rows));
+}
+
+async function submit() {
+    planIsStale();
+    const started = await api("POST", "/posts", posts());
+    state.jobId = started.job_id;
+    $("cancel").hidden = false;
+    const container = $("job-progress");
+    try {
+        const job = await followJob(started.job_id, (job) => renderJob(container, job));
+        if (Array.isArray(job?.result)) {
+            renderReport(container, job.result);
+        }
+        showMessage(`Submission ${job?.status ?? "stopped"}.`, job?.status === "failed");
+    } finally {
+        $("cancel").hidden = true;
+        state.jobId = null;
+    }
+}
+
+async function cancel() {
+    if (state.jobId) {
+        await api("DELETE", `/jobs/${state.jobId}`);
+        showMessage("Cancelling after the current post.");
+    }
+}
+
+// Wiring
+
+function guarded(action) {
+    return async (event) => {
+        const button = event?.currentTarget instanceof HTMLButtonElement ? event.currentTarget : null;
+        if (button) {
+            button.disabled = true;
+        }
+        try {
+            showMessage("");
+            await action(event);
+        } catch (error) {
+            showMessage(error.message, true);
+        } finally {
+            if (button && button.id !== "submit") {
+                button.disabled = false;
+            }
+        }
+    };
+}
+
+async function start() {
+    $("api-key").value = sessionStorage.getItem("apiKey") ?? "";
+    $("api-key").addEventListener("change", guarded(async () => {
+        sessionStorage.setItem("apiKey", $("api-key").value);
+        await load();
+    }));
+    $("accounts").addEventListener("change", () => {
+        sessionStorage.setItem("account", $("accounts").value);
+        planIsStale();
+    });
+    $("login").addEventListener("click", guarded(login));
+    $("add-subreddit").addEventListener("submit", addSubreddit);
+    $("load-flairs").addEventListener("click", guarded(loadFlairs));
+    $("preview").addEventListener("click", guarded(preview));
+    $("submit").addEventListener("click", guarded(submit));
+    $("cancel").addEventListener("click", guarded(cancel));
+    $("post").addEventListener("input", planIsStale);
+    await load();
+}
+
+async function load() {
+    await loadAccounts();
+    for (const name of await api("GET", "/subreddits")) {
+        if (!state.subreddits.includes(name)) {
+            state.subreddits.push(name);
+        }
+    }
+    renderSubreddits();
+    renderTargets();
+}
+
+start().catch((error) => showMessage(error.message, true));